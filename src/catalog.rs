@@ -1,4 +1,6 @@
 use labdef::*;
+use labfit::Complexity;
+use labobs::list_observers;
 use adapton::collections::*;
 use adapton::engine::*;
 use rand::{Rng};
@@ -8,11 +10,24 @@ use pmfp_collections::gauged_raz::{Raz,RazTree};
 use pmfp_collections::level_tree::{gen_branch_level};
 
 
+/// Checks the parameters of the editors below, which place names
+/// regularly, every `gauge` elements.
+fn check_regular_params(params:&GenerateParams) -> Result<(), EditError> {
+  if params.gauge == 0 {
+    return Err(EditError::InvalidParams(String::from("gauge == 0")))
+  };
+  match params.nominal_strategy {
+    NominalStrategy::Regular => Ok(()),
+    ref st => Err(EditError::UnsupportedStrategy(st.clone())),
+  }
+}
+
 #[derive(Clone,Debug)]
 pub struct UniformInsert<T,S> { t:PhantomData<T>, s:PhantomData<S> }
 
 impl<S> Generate<RazTree<usize>> for UniformInsert<RazTree<usize>, S> {
-  fn generate<R:Rng> (mut rng:&mut R, params:&GenerateParams) -> RazTree<usize> {
+  fn generate<R:Rng> (mut rng:&mut R, params:&GenerateParams) -> Result<RazTree<usize>, EditError> {
+    check_regular_params(params)?;
     let mut r = Raz::new();
     for i in 0..params.size {
       if i % params.gauge == 0 {
@@ -21,7 +36,7 @@ impl<S> Generate<RazTree<usize>> for UniformInsert<RazTree<usize>, S> {
       } else { } ;
       r.push_left(i);
     }
-    r.unfocus()
+    Ok(r.unfocus())
   }
 }
 
@@ -30,13 +45,17 @@ impl Edit<RazTree<usize>, usize> for UniformInsert<RazTree<usize>, usize> {
     return params.size // Initial editing state = The size of the generated input
   }
   fn edit<R:Rng>(tree:RazTree<usize>, i:usize,
-                 rng:&mut R, _params:&GenerateParams) -> (RazTree<usize>, usize) {
+                 rng:&mut R, params:&GenerateParams) -> Result<(RazTree<usize>, usize), EditError> {
+    check_regular_params(params)?;
     let t = tree;
     let pos = rng.gen::<usize>() % ( i + 1 );
-    let mut r = t.focus( pos ).unwrap();
+    let mut r = match t.focus( pos ) {
+      Some(r) => r,
+      None => return Err(EditError::Impossible(format!("cannot focus at position {} of {}", pos, i))),
+    };
     r.push_left( rng.gen() );
     let t = r.unfocus();    
-    (t, i + 1)
+    Ok((t, i + 1))
   }
 }
 
@@ -46,7 +65,8 @@ impl Edit<RazTree<usize>, usize> for UniformInsert<RazTree<usize>, usize> {
 pub struct UniformPrepend<T,S> { t:PhantomData<T>, s:PhantomData<S> }
 
 impl<S> Generate<List<usize>> for UniformPrepend<List<usize>,S> {
-  fn generate<R:Rng>(rng:&mut R, params:&GenerateParams) -> List<usize> {
+  fn generate<R:Rng>(rng:&mut R, params:&GenerateParams) -> Result<List<usize>, EditError> {
+    check_regular_params(params)?;
     let mut l : List<usize> = list_nil();
     for i in 0..params.size {
       if i % params.gauge == 0 {
@@ -61,7 +81,7 @@ impl<S> Generate<List<usize>> for UniformPrepend<List<usize>,S> {
         //l = list_name(name_of_usize(i), l);
       } else { } ;
     } ;
-    l
+    Ok(l)
   }
 }
 
@@ -71,7 +91,8 @@ impl Edit<List<usize>, usize> for UniformPrepend<List<usize>,usize> {
   }
   fn edit<R:Rng>(l_preedit:List<usize>, 
                  next_name:usize,
-                 rng:&mut R, params:&GenerateParams) -> (List<usize>, usize) {
+                 rng:&mut R, params:&GenerateParams) -> Result<(List<usize>, usize), EditError> {
+    check_regular_params(params)?;
     let mut l = l_preedit ;
     let i = next_name ;
     if i % params.gauge == 0 {
@@ -85,7 +106,7 @@ impl Edit<List<usize>, usize> for UniformPrepend<List<usize>,usize> {
       //l = list_art(cell(name_of_usize(i), l));
       //l = list_name(name_of_usize(i), l);      
     } else { } ;
-    (l, i + 1)
+    Ok((l, i + 1))
   }
 }
 
@@ -96,17 +117,15 @@ impl Edit<List<usize>, usize> for UniformPrepend<List<usize>,usize> {
 type Pt2D = (usize,usize); // TODO Fix this
 
 impl<S> Generate<List<Pt2D>> for UniformPrepend<List<Pt2D>,S> { // TODO
-  fn generate<R:Rng>(_rng:&mut R, _params:&GenerateParams) -> List<Pt2D> {
-    //panic!("TODO")
-    list_nil()
+  fn generate<R:Rng>(_rng:&mut R, _params:&GenerateParams) -> Result<List<Pt2D>, EditError> {
+    Err(EditError::Unimplemented)
   }
 }
 
 impl Edit<List<Pt2D>,usize> for UniformPrepend<List<Pt2D>,usize> { // TODO
   fn edit_init<R:Rng>(_rng:&mut R, _params:&GenerateParams) -> usize { 0 }
-  fn edit<R:Rng>(state:List<Pt2D>, st:usize, _rng:&mut R, _params:&GenerateParams) -> (List<Pt2D>, usize) {
-    //TODO
-    (state, st)
+  fn edit<R:Rng>(_state:List<Pt2D>, _st:usize, _rng:&mut R, _params:&GenerateParams) -> Result<(List<Pt2D>, usize), EditError> {
+    Err(EditError::Unimplemented)
   }
 }
 
@@ -151,31 +170,49 @@ pub struct Quickhull { }
 
 pub struct RazTest1 {} 
 
-impl Compute<List<usize>,List<usize>> for EagerMap {
-  fn compute(inp:List<usize>) -> List<usize> {
-    list_map_eager(inp,Rc::new(|x| x * x))
+/// The map labs raise each element to the power of the `map-pow`
+/// knob (default 2, i.e., squaring).
+fn map_pow(ctx:&ComputeContext) -> usize { ctx.knobs.get("map-pow", 2) }
+
+/// Like the squaring that it generalizes, panics on overflow (in
+/// debug builds).
+fn power(x:usize, pow:usize) -> usize {
+  (0..pow).fold(1, |acc:usize, _| acc * x)
+}
+
+/// The filter labs keep the multiples of the `filter-modulus` knob
+/// (default 3).
+fn filter_modulus(ctx:&ComputeContext) -> usize { ctx.knobs.get("filter-modulus", 3) }
+
+impl ComputeCtx<List<usize>,List<usize>> for EagerMap {
+  fn compute(inp:List<usize>, ctx:&ComputeContext) -> List<usize> {
+    let pow = map_pow(ctx);
+    list_map_eager(inp,Rc::new(move |x| power(x, pow)))
   }
 }
 
-impl Compute<List<usize>,List<usize>> for EagerFilter {
-  fn compute(inp:List<usize>) -> List<usize> {
-    list_filter_eager(inp,Rc::new(|x:&usize| (*x) % 3 == 0))
+impl ComputeCtx<List<usize>,List<usize>> for EagerFilter {
+  fn compute(inp:List<usize>, ctx:&ComputeContext) -> List<usize> {
+    let modulus = filter_modulus(ctx);
+    list_filter_eager(inp,Rc::new(move |x:&usize| (*x) % modulus == 0))
   }
 }
 
-impl ComputeDemand<List<usize>,List<usize>> for LazyMap {
-  fn compute(inp:List<usize>, demand:usize) -> List<usize> {
-    let out : List<usize> = list_map_lazy(inp,Rc::new(|x| x * x));
-    drop( list_demand( out.clone(), demand ) );
+impl ComputeCtx<List<usize>,List<usize>> for LazyMap {
+  fn compute(inp:List<usize>, ctx:&ComputeContext) -> List<usize> {
+    let pow = map_pow(ctx);
+    let out : List<usize> = list_map_lazy(inp,Rc::new(move |x| power(x, pow)));
+    drop( list_demand( out.clone(), ctx.sample_params.demand ) );
     out
   }
 }
 
-impl ComputeDemand<List<usize>,List<usize>> for LazyFilter {
-  fn compute(inp:List<usize>, demand:usize) -> List<usize> {
+impl ComputeCtx<List<usize>,List<usize>> for LazyFilter {
+  fn compute(inp:List<usize>, ctx:&ComputeContext) -> List<usize> {
+    let modulus = filter_modulus(ctx);
     let out : List<usize> = 
-      list_filter_lazy(inp,Rc::new(|x:&usize| (*x) % 3 == 0));
-    drop( list_demand( out.clone(), demand) );
+      list_filter_lazy(inp,Rc::new(move |x:&usize| (*x) % modulus == 0));
+    drop( list_demand( out.clone(), ctx.sample_params.demand) );
     out
   }
 }
@@ -308,19 +345,30 @@ impl Compute<List<Pt2D>,List<Pt2D>> for Quickhull {
 
 #[macro_export]
 macro_rules! labdef {
-  ( $name:expr, $url:expr, $inp:ty, $editst:ty, $out:ty, $dist:ty, $comp:ty ) => {{ 
+  ( $name:expr, $url:expr, $inp:ty, $editst:ty, $out:ty, $dist:ty, $comp:ty, $meta:expr ) => {{ 
     Box::new( 
-      LabDef
-        ::<$inp,$editst,$out,$dist,$comp>
+      $crate::labdef::LabDef
+        ::<$inp,$editst,$out,$dist,$comp,$crate::labdef::StructEq>
       { 
         identity:$name,
         url:$url,
-        input:PhantomData,
-        editst:PhantomData,
-        output:PhantomData,
-        editor:PhantomData,
-        archivist:PhantomData
+        meta:$crate::labdef::LabMeta{
+          input_type:String::from(stringify!($inp)),
+          output_type:String::from(stringify!($out)),
+          .. $meta
+        },
+        knobs:$crate::labdef::LabKnobs::default(),
+        observers:vec![],
+        input: ::std::marker::PhantomData,
+        editst: ::std::marker::PhantomData,
+        output: ::std::marker::PhantomData,
+        editor: ::std::marker::PhantomData,
+        archivist: ::std::marker::PhantomData,
+        equiv: ::std::marker::PhantomData
       }) 
+  }};
+  ( $name:expr, $url:expr, $inp:ty, $editst:ty, $out:ty, $dist:ty, $comp:ty ) => {{ 
+    labdef!($name, $url, $inp, $editst, $out, $dist, $comp, $crate::labdef::LabMeta::default())
   }}
 }
 
@@ -347,6 +395,19 @@ macro_rules! labdef {
 /// url to display in generated output.  The URL should link to the
 /// rustdoc for this module, which in turn provides other related
 /// documentation about Adapton Lab and Adapton.
+///
+/// 4. Optionally, a last argument gives the lab's `LabMeta`: its
+/// description, tags, and expected complexity (`labdef!` fills in
+/// the type names).
+///
+/// By default, we validate outputs by equality; to validate them
+/// otherwise, e.g., by content, follow `labdef!` with `with_equiv`
+/// (See `labdef::OutputEquiv`).  Likewise, to observe the output in
+/// other ways than `demand`, follow it with `with_observers` (See
+/// `labobs`).
+///
+/// Alternatively, to avoid defining these types, build the lab from
+/// closures with a `labdef::LabBuilder`, and add its `build()` here.
 // - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - 
 pub fn all_labs() -> Vec<Box<Lab>> {
   return vec![
@@ -355,14 +416,28 @@ pub fn all_labs() -> Vec<Box<Lab>> {
                   List<usize>, usize,
                   List<usize>,
                   UniformPrepend<_,_>,
-                  LazyMap)
+                  LazyMap,
+                  LabMeta{
+                    descr:String::from("Lazily maps a power (knob map-pow, default 2) over a list; forces a prefix of the output."),
+                    tags:vec![ LabTag::List, LabTag::Lazy ],
+                    honors_demand:true,
+                    update_complexity:Some(Complexity::Const),
+                    .. LabMeta::default()
+                  }).with_observers(list_observers(4))
       ,
     labdef!(name_of_str("list-lazy-filter"),
                   Some(String::from("http://adapton.org/rustdoc/adapton_lab/catalog/struct.LazyFilter.html")),
                   List<usize>, usize,
                   List<usize>,
                   UniformPrepend<_,_>,
-                  LazyFilter)
+                  LazyFilter,
+                  LabMeta{
+                    descr:String::from("Lazily filters a list for multiples (of knob filter-modulus, default 3); forces a prefix of the output."),
+                    tags:vec![ LabTag::List, LabTag::Lazy ],
+                    honors_demand:true,
+                    update_complexity:Some(Complexity::Const),
+                    .. LabMeta::default()
+                  }).with_observers(list_observers(4))
       ,
 
 
@@ -371,21 +446,42 @@ pub fn all_labs() -> Vec<Box<Lab>> {
                   List<usize>, usize,
                   Tree<usize>,
                   UniformPrepend<_,_>,
-                  ListTree)
+                  ListTree,
+                  LabMeta{
+                    descr:String::from("Converts a list into a balanced tree, using the list's names; validated by structure and by content."),
+                    tags:vec![ LabTag::List, LabTag::Tree, LabTag::Eager ],
+                    honors_demand:false,
+                    update_complexity:Some(Complexity::Log),
+                    .. LabMeta::default()
+                  }).with_equiv::<Both<StructEq,SeqContent>>()
       ,
     labdef!(name_of_str("list-tree-max"),
                   Some(String::from("http://adapton.org/rustdoc/adapton_lab/catalog/struct.ListTreeMax.html")),
                   List<usize>, usize,
                   usize,
                   UniformPrepend<_,_>,
-                  ListTreeMax)
+                  ListTreeMax,
+                  LabMeta{
+                    descr:String::from("Computes the maximum of a list, via a balanced tree."),
+                    tags:vec![ LabTag::List, LabTag::Tree, LabTag::Eager ],
+                    honors_demand:false,
+                    update_complexity:Some(Complexity::Log),
+                    .. LabMeta::default()
+                  })
       ,
     labdef!(name_of_str("list-tree-sum"),
                   Some(String::from("http://adapton.org/rustdoc/adapton_lab/catalog/struct.ListTreeSum.html")),
                   List<usize>, usize,
                   usize,
                   UniformPrepend<_,_>,
-                  ListTreeSum)
+                  ListTreeSum,
+                  LabMeta{
+                    descr:String::from("Computes the sum of a list, via a balanced tree."),
+                    tags:vec![ LabTag::List, LabTag::Tree, LabTag::Eager ],
+                    honors_demand:false,
+                    update_complexity:Some(Complexity::Log),
+                    .. LabMeta::default()
+                  })
       ,
 
     labdef!(name_of_str("list-eager-mergesort3"),
@@ -393,14 +489,28 @@ pub fn all_labs() -> Vec<Box<Lab>> {
                   List<usize>, usize,
                   List<usize>,
                   UniformPrepend<_,_>,
-                  EagerMergesort3)
+                  EagerMergesort3,
+                  LabMeta{
+                    descr:String::from("Sorts a list, via a balanced tree, with mergesort (variant 3); forces all of the output."),
+                    tags:vec![ LabTag::List, LabTag::Tree, LabTag::Sort, LabTag::Eager ],
+                    honors_demand:false,
+                    update_complexity:Some(Complexity::LogSquared),
+                    .. LabMeta::default()
+                  })
       ,
     labdef!(name_of_str("list-lazy-mergesort3"),
                   Some(String::from("http://adapton.org/rustdoc/adapton_lab/catalog/struct.LazyMergesort3.html")),
                   List<usize>, usize,
                   List<usize>,
                   UniformPrepend<_,_>,
-                  LazyMergesort3)
+                  LazyMergesort3,
+                  LabMeta{
+                    descr:String::from("Sorts a list, via a balanced tree, with mergesort (variant 3); forces a prefix of the output."),
+                    tags:vec![ LabTag::List, LabTag::Tree, LabTag::Sort, LabTag::Lazy ],
+                    honors_demand:true,
+                    update_complexity:Some(Complexity::Log),
+                    .. LabMeta::default()
+                  }).with_observers(list_observers(4))
       ,
 
     labdef!(name_of_str("list-eager-mergesort2"),
@@ -408,14 +518,28 @@ pub fn all_labs() -> Vec<Box<Lab>> {
                   List<usize>, usize,
                   List<usize>,
                   UniformPrepend<_,_>,
-                  EagerMergesort2)
+                  EagerMergesort2,
+                  LabMeta{
+                    descr:String::from("Sorts a list, via a balanced tree, with mergesort (variant 2); forces all of the output."),
+                    tags:vec![ LabTag::List, LabTag::Tree, LabTag::Sort, LabTag::Eager ],
+                    honors_demand:false,
+                    update_complexity:Some(Complexity::LogSquared),
+                    .. LabMeta::default()
+                  })
       ,
     labdef!(name_of_str("list-lazy-mergesort2"),
                   Some(String::from("http://adapton.org/rustdoc/adapton_lab/catalog/struct.LazyMergesort2.html")),
                   List<usize>, usize,
                   List<usize>,
                   UniformPrepend<_,_>,
-                  LazyMergesort2)
+                  LazyMergesort2,
+                  LabMeta{
+                    descr:String::from("Sorts a list, via a balanced tree, with mergesort (variant 2); forces a prefix of the output."),
+                    tags:vec![ LabTag::List, LabTag::Tree, LabTag::Sort, LabTag::Lazy ],
+                    honors_demand:true,
+                    update_complexity:Some(Complexity::Log),
+                    .. LabMeta::default()
+                  })
       ,
 
     labdef!(name_of_str("list-eager-mergesort1"),
@@ -423,14 +547,28 @@ pub fn all_labs() -> Vec<Box<Lab>> {
                   List<usize>, usize,
                   List<usize>,
                   UniformPrepend<_,_>,
-                  EagerMergesort1)
+                  EagerMergesort1,
+                  LabMeta{
+                    descr:String::from("Sorts a list, via a balanced tree, with mergesort (variant 1); forces all of the output."),
+                    tags:vec![ LabTag::List, LabTag::Tree, LabTag::Sort, LabTag::Eager ],
+                    honors_demand:false,
+                    update_complexity:None,
+                    .. LabMeta::default()
+                  })
       ,
     labdef!(name_of_str("list-lazy-mergesort1"),
                   Some(String::from("http://adapton.org/rustdoc/adapton_lab/catalog/struct.LazyMergesort1.html")),
                   List<usize>, usize,
                   List<usize>,
                   UniformPrepend<_,_>,
-                  LazyMergesort1)
+                  LazyMergesort1,
+                  LabMeta{
+                    descr:String::from("Sorts a list, via a balanced tree, with mergesort (variant 1); forces a prefix of the output."),
+                    tags:vec![ LabTag::List, LabTag::Tree, LabTag::Sort, LabTag::Lazy ],
+                    honors_demand:true,
+                    update_complexity:None,
+                    .. LabMeta::default()
+                  })
       ,
 
     labdef!(name_of_str("list-eager-map"),
@@ -438,21 +576,56 @@ pub fn all_labs() -> Vec<Box<Lab>> {
                   List<usize>, usize,
                   List<usize>,
                   UniformPrepend<_,_>,
-                  EagerMap)
+                  EagerMap,
+                  LabMeta{
+                    descr:String::from("Eagerly maps a power (knob map-pow, default 2) over a list."),
+                    tags:vec![ LabTag::List, LabTag::Eager ],
+                    honors_demand:false,
+                    update_complexity:Some(Complexity::Const),
+                    .. LabMeta::default()
+                  })
       ,
     labdef!(name_of_str("list-eager-filter"),
                   Some(String::from("http://adapton.org/rustdoc/adapton_lab/catalog/struct.EagerFilter.html")),
                   List<usize>, usize,
                   List<usize>,
                   UniformPrepend<_,_>,
-                  EagerFilter)
+                  EagerFilter,
+                  LabMeta{
+                    descr:String::from("Eagerly filters a list for multiples (of knob filter-modulus, default 3)."),
+                    tags:vec![ LabTag::List, LabTag::Eager ],
+                    honors_demand:false,
+                    update_complexity:Some(Complexity::Const),
+                    .. LabMeta::default()
+                  })
+      ,
+    labdef!(name_of_str("list-eager-filter-mod2"),
+                  Some(String::from("http://adapton.org/rustdoc/adapton_lab/catalog/struct.EagerFilter.html")),
+                  List<usize>, usize,
+                  List<usize>,
+                  UniformPrepend<_,_>,
+                  EagerFilter,
+                  LabMeta{
+                    descr:String::from("Eagerly filters a list for multiples of two (knob filter-modulus)."),
+                    tags:vec![ LabTag::List, LabTag::Eager ],
+                    honors_demand:false,
+                    update_complexity:Some(Complexity::Const),
+                    .. LabMeta::default()
+                  }).with_knob("filter-modulus", 2)
       ,
     labdef!(name_of_str("list-reverse"),
                   Some(String::from("http://adapton.org/rustdoc/adapton_lab/catalog/struct.ListReverse.html")),
                   List<usize>, usize,
                   List<usize>,
                   UniformPrepend<_,_>,
-                  ListReverse)
+                  ListReverse,
+                  LabMeta{
+                    descr:String::from("Reverses a list, with an accumulator; a prepended element changes the accumulator for every step."),
+                    tags:vec![ LabTag::List, LabTag::Eager ],
+                    honors_demand:false,
+                    update_complexity:Some(Complexity::Linear),
+                    .. LabMeta::default()
+                  })
       ,
     // labdef!(name_of_str("list-quickhull"),
     //               List<Pt2D>, usize,
@@ -462,3 +635,151 @@ pub fn all_labs() -> Vec<Box<Lab>> {
     // ,
   ]
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lab_params_defaults;
+  use labobs::{Observer, list_last, list_traverse};
+  use labrng::{LabRngGen, Pcg32};
+
+  /// Builds `list-eager-map` from closures (See `LabBuilder`), and runs it.
+  #[test]
+  fn builder_list_eager_map() {
+    type Prepend = UniformPrepend<List<usize>,usize>;
+    let lab = LabBuilder::new(name_of_str("builder-list-eager-map"))
+      .knob("map-pow", 3)
+      .generator(|rng:&mut Rng, params:&GenerateParams| {
+        let mut rng = rng;
+        <Prepend as Generate<List<usize>>>::generate(&mut rng, params)
+      })
+      .editor(|rng:&mut Rng, params:&GenerateParams| {
+        let mut rng = rng;
+        <Prepend as Edit<List<usize>,usize>>::edit_init(&mut rng, params)
+      }, |input:List<usize>, st:usize, rng:&mut Rng, params:&GenerateParams| {
+        let mut rng = rng;
+        <Prepend as Edit<List<usize>,usize>>::edit(input, st, &mut rng, params)
+      })
+      .compute_ctx(|input:List<usize>, ctx:&ComputeContext| {
+        <EagerMap as ComputeCtx<List<usize>,List<usize>>>::compute(input, ctx)
+      })
+      .build();
+    assert_eq!( lab.knobs().get("map-pow", 2), 3 );
+    let params = lab_params_defaults();
+    let results = lab.run(&params);
+    assert_eq!( results.errors.len(), 0 );
+    assert_eq!( results.samples.len(), params.change_batch_loopc + 1 );
+    for sample in results.samples.iter() {
+      assert_eq!( sample.input_valid, Some(true) );
+      assert_eq!( sample.output_valid, Some(true) );
+    }
+  }
+
+  fn list_lazy_map(observers:Vec<Observer<List<usize>>>) -> Box<Lab> {
+    labdef!(name_of_str("list-lazy-map"), None,
+            List<usize>, usize, List<usize>,
+            UniformPrepend<_,_>, LazyMap,
+            LabMeta{ honors_demand:true, .. LabMeta::default() })
+      .with_observers(observers)
+  }
+
+  /// Each observer runs in its own shadow of the engine, so its costs
+  /// do not depend on the other observers, nor does it perturb the
+  /// costs of the measured computation.
+  #[test]
+  fn observers_are_isolated() {
+    let params = lab_params_defaults();
+    let none  = list_lazy_map(vec![]).run(&params);
+    let alone = list_lazy_map(vec![ list_traverse() ]).run(&params);
+    let after = list_lazy_map(vec![ list_last(), list_traverse() ]).run(&params);
+    for results in vec![ &none, &alone, &after ] {
+      assert_eq!( results.errors.len(), 0 );
+      assert_eq!( results.samples.len(), params.change_batch_loopc + 1 );
+    };
+    for ((s0, s1), s2) in none.samples.iter().zip(alone.samples.iter()).zip(after.samples.iter()) {
+      for ((e0, e1), e2) in s0.engine_samples.iter().zip(s1.engine_samples.iter()).zip(s2.engine_samples.iter()) {
+        let (c0, c1, c2) = (&e0.compute_output.engine_cnt, &e1.compute_output.engine_cnt, &e2.compute_output.engine_cnt);
+        assert_eq!( (c0.eval, c0.create), (c1.eval, c1.create) );
+        assert_eq!( (c0.eval, c0.create), (c2.eval, c2.create) );
+        let (o1, o2) = (&e1.observations[0], &e2.observations[1]);
+        assert_eq!( (o1.name.as_str(), o2.name.as_str()), ("traverse", "traverse") );
+        assert_eq!( o1.valid, Some(true) );
+        assert_eq!( o2.valid, Some(true) );
+        let (c1, c2) = (&o1.metrics.engine_cnt, &o2.metrics.engine_cnt);
+        assert_eq!( (c1.eval, c1.create), (c2.eval, c2.create) );
+      }
+    }
+  }
+
+  /// Batches that change only the demand leave the input unchanged,
+  /// and do not count as edit batches: otherwise, the from-scratch
+  /// engine, which replays each prior edit batch, would edit its
+  /// input once too often, and its input would differ from the
+  /// reference engine's.
+  #[test]
+  fn demand_change_keeps_input() {
+    let mut params = lab_params_defaults();
+    params.demand_change = Some(DemandChange{ period:2, max_demand:20 });
+    let results = list_lazy_map(list_observers(4)).run(&params);
+    assert_eq!( results.errors.len(), 0 );
+    assert_eq!( results.samples.len(), params.change_batch_loopc + 1 );
+    let mut demand_only_cnt = 0;
+    for (prev, this) in results.samples.iter().zip(results.samples.iter().skip(1)) {
+      assert_eq!( this.demand_only, this.batch_name % 2 == 0 );
+      let input_of = |s:&Sample| s.engine_sample(&EngineConfig::DCG).unwrap().input.clone().unwrap();
+      if this.demand_only {
+        demand_only_cnt += 1;
+        assert!( this.demand <= 20 );
+        assert!( input_of(prev) == input_of(this) );
+      } else {
+        assert_eq!( this.demand, prev.demand );
+        assert!( input_of(prev) != input_of(this) );
+      };
+    };
+    assert_eq!( demand_only_cnt, params.change_batch_loopc / 2 );
+    for sample in results.samples.iter() {
+      assert_eq!( sample.input_valid, Some(true) );
+      assert_eq!( sample.output_valid, Some(true) );
+      let from_scratch = sample.engine_sample(&EngineConfig::DCGFromScratch).unwrap();
+      assert_eq!( from_scratch.input_valid, Some(true) );
+      assert_eq!( from_scratch.output_valid, Some(true) );
+    }
+  }
+
+  /// An editor that rejects its parameters stops the lab in its first
+  /// batch, with one error (from the first engine), and no samples.
+  #[test]
+  fn zero_gauge_is_one_error() {
+    let mut params = lab_params_defaults();
+    params.sample_params.generate_params.gauge = 0;
+    let lab = all_labs().into_iter().find(|l| l.name() == name_of_str("list-eager-map")).unwrap();
+    let results = lab.run(&params);
+    assert_eq!( results.samples.len(), 0 );
+    assert_eq!( results.errors.len(), 1 );
+    assert_eq!( results.errors[0].batch_name, 0 );
+    assert_eq!( results.errors[0].engine, params.engines[0] );
+    match results.errors[0].error {
+      EditError::InvalidParams(_) => (),
+      ref e => panic!("expected invalid params, not {:?}", e),
+    }
+  }
+
+  /// The editor of lists of points is not implemented, yet.
+  #[test]
+  fn pt2d_editor_is_unimplemented() {
+    type Prepend = UniformPrepend<List<Pt2D>,usize>;
+    let params = lab_params_defaults();
+    let mut rng = Pcg32::from_seeds(&vec![0]);
+    assert_eq!( <Prepend as Generate<List<Pt2D>>>::generate(&mut rng, &params.sample_params.generate_params).err(),
+                Some(EditError::Unimplemented) );
+    assert_eq!( <Prepend as Edit<List<Pt2D>,usize>>::edit(list_nil(), 0, &mut rng, &params.sample_params.generate_params).err(),
+                Some(EditError::Unimplemented) );
+    let lab = labdef!(name_of_str("list-quickhull"), None,
+                      List<Pt2D>, usize, List<Pt2D>,
+                      UniformPrepend<_,_>, Quickhull);
+    let results = lab.run(&params);
+    assert_eq!( results.samples.len(), 0 );
+    assert_eq!( results.errors.len(), 1 );
+    assert_eq!( results.errors[0].error, EditError::Unimplemented );
+  }
+}
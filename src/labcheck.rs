@@ -0,0 +1,324 @@
+use labdef::*;
+use labviz::string_of_trace_effect;
+
+use std::collections::HashMap;
+
+use adapton::engine::Cnt;
+use adapton::engine::reflect::{DCG, Loc, Node, Effect, succs_of_node};
+use adapton::engine::reflect::trace;
+use adapton::engine::reflect::string_of_loc;
+
+/// What differs between two runs of the same lab, with identical
+/// parameters.
+#[derive(Clone,Debug)]
+pub enum DivergenceKind {
+  /// The runs produced different numbers of samples.
+  SampleCount(usize, usize),
+  /// The runs' editors failed differently (See `LabResults::errors`).
+  Errors(Vec<LabError>, Vec<LabError>),
+  /// The runs' progresses stopped them differently (See
+  /// `LabResults::stopped`).
+  Stopped(Option<String>, Option<String>),
+  /// The engine counters of the given role differ.
+  Cnt(Role, Cnt, Cnt),
+  /// The reflected traces of the given role differ in shape; the
+  /// string describes the first difference.
+  Trace(Role, String),
+  /// The reflected inputs differ.
+  Input,
+  /// The reflected outputs differ.
+  Output,
+}
+
+/// The first point at which two runs of the same lab, with identical
+/// parameters, diverge.
+#[derive(Clone,Debug)]
+pub struct Divergence {
+  pub batch_name: usize,
+  /// None when the divergence is not specific to one engine.
+  pub engine:     Option<EngineConfig>,
+  pub kind:       DivergenceKind,
+}
+
+/// Compares two trace forests by shape: their effects, the locations
+/// of their edges, and their nesting.  Returns a description of the
+/// first difference, if any.
+pub fn trace_divergence(trs1:&Vec<trace::Trace>, trs2:&Vec<trace::Trace>) -> Option<String> {
+  if trs1.len() != trs2.len() {
+    return Some(format!("{} vs {} traces", trs1.len(), trs2.len()))
+  };
+  for (tr1, tr2) in trs1.iter().zip(trs2.iter()) {
+    let eff1 = string_of_trace_effect(&tr1.effect);
+    let eff2 = string_of_trace_effect(&tr2.effect);
+    if eff1 != eff2 {
+      return Some(format!("{} vs {}", eff1, eff2))
+    };
+    if tr1.edge.succ.loc != tr2.edge.succ.loc {
+      return Some(format!("{} at {} vs {}", eff1,
+                          string_of_loc(&tr1.edge.succ.loc),
+                          string_of_loc(&tr2.edge.succ.loc)))
+    };
+    match trace_divergence(&tr1.extent, &tr2.extent) {
+      None => continue,
+      Some(d) => return Some(format!("{} at {}: {}", eff1, string_of_loc(&tr1.edge.succ.loc), d)),
+    }
+  };
+  None
+}
+
+/// Compares two samples of the same engine, role by role: counts,
+/// trace shapes, and then reflected inputs and outputs.
+fn engine_sample_divergence(s1:&EngineSample, s2:&EngineSample) -> Option<DivergenceKind> {
+  for role in vec![Role::Editor, Role::Archivist] {
+    let (m1, m2) = (s1.metrics(&role), s2.metrics(&role));
+    if m1.engine_cnt != m2.engine_cnt {
+      return Some(DivergenceKind::Cnt(role.clone(), m1.engine_cnt.clone(), m2.engine_cnt.clone()))
+    };
+    match trace_divergence(&m1.reflect_traces, &m2.reflect_traces) {
+      None => (),
+      Some(d) => return Some(DivergenceKind::Trace(role.clone(), d)),
+    }
+  };
+  if s1.input != s2.input { return Some(DivergenceKind::Input) };
+  if s1.output != s2.output { return Some(DivergenceKind::Output) };
+  None
+}
+
+/// Whether two editor failures are the same failure.
+fn same_lab_error(e1:&LabError, e2:&LabError) -> bool {
+  e1.batch_name == e2.batch_name && e1.engine == e2.engine && e1.error == e2.error
+}
+
+/// Compares the results of two runs of the same lab, batch by batch,
+/// and engine by engine; then, how each run ended: its editor
+/// failures, the reason that it stopped early, and its number of
+/// samples.  Returns the first divergence, if any.
+pub fn results_divergence(r1:&LabResults, r2:&LabResults) -> Option<Divergence> {
+  for (s1, s2) in r1.samples.iter().zip(r2.samples.iter()) {
+    for (es1, es2) in s1.engine_samples.iter().zip(s2.engine_samples.iter()) {
+      match engine_sample_divergence(es1, es2) {
+        None => continue,
+        Some(kind) => return Some(Divergence{
+          batch_name: s1.batch_name,
+          engine: Some(es1.engine.clone()),
+          kind,
+        })
+      }
+    }
+  };
+  // The batch after the samples that both runs share.
+  let batch_name = if r1.samples.len() < r2.samples.len() { r1.samples.len() } else { r2.samples.len() };
+  if r1.errors.len() != r2.errors.len() ||
+    !r1.errors.iter().zip(r2.errors.iter()).all(|(e1, e2)| same_lab_error(e1, e2)) {
+      return Some(Divergence{
+        batch_name,
+        engine: None,
+        kind: DivergenceKind::Errors(r1.errors.clone(), r2.errors.clone()),
+      })
+    };
+  if r1.stopped != r2.stopped {
+    return Some(Divergence{
+      batch_name,
+      engine: None,
+      kind: DivergenceKind::Stopped(r1.stopped.clone(), r2.stopped.clone()),
+    })
+  };
+  if r1.samples.len() != r2.samples.len() {
+    return Some(Divergence{
+      batch_name,
+      engine: None,
+      kind: DivergenceKind::SampleCount(r1.samples.len(), r2.samples.len()),
+    })
+  };
+  None
+}
+
+/// Determinism check: Runs the lab twice, with identical parameters,
+/// and compares the two results.  Experiments should be deterministic
+/// given `SampleParams::input_seeds`; a divergence often indicates
+/// that names depend on hash order or pointer addresses.  Trace
+/// shapes are only compared when `reflect_trace` is set, and inputs
+/// and outputs only when `reflect_dcg` is set.
+pub fn check_determinism(lab:&Box<Lab>, params:&LabParams) -> Option<Divergence> {
+  let r1 = lab.run(params);
+  let r2 = lab.run(params);
+  results_divergence(&r1, &r2)
+}
+
+/// A structured diagnostic about the well-formedness of a reflected
+/// DCG (See `adapton::engine::reflect::DCG`).
+#[derive(Clone,Debug)]
+pub enum DcgDiagnostic {
+  /// A successor edge, from the first location to the second, whose
+  /// target is not in the DCG's table.
+  DanglingSucc(Loc, Loc),
+  /// A dirty force edge, from the first location to the second, that
+  /// remains reachable from a root, via clean force edges, after the
+  /// update completes.
+  DirtyAfterUpdate(Loc, Loc),
+  /// A computation node that some clean edge forced, but which has no value.
+  ForcedWithoutValue(Loc),
+  /// A cycle of successor edges, listed in order.
+  Cycle(Vec<Loc>),
+}
+
+impl DcgDiagnostic {
+  /// A short, human-readable description, e.g., for generated output.
+  pub fn descr(self:&Self) -> String {
+    match *self {
+      DcgDiagnostic::DanglingSucc(ref src, ref tgt) => 
+        format!("Dangling successor: {} --> {}", string_of_loc(src), string_of_loc(tgt)),
+      DcgDiagnostic::DirtyAfterUpdate(ref src, ref tgt) => 
+        format!("Dirty after update: {} --> {}", string_of_loc(src), string_of_loc(tgt)),
+      DcgDiagnostic::ForcedWithoutValue(ref loc) => 
+        format!("Forced without value: {}", string_of_loc(loc)),
+      DcgDiagnostic::Cycle(ref locs) => 
+        format!("Cycle: {}", locs.iter().map(string_of_loc).collect::<Vec<_>>().join(" --> ")),
+    }
+  }
+}
+
+/// The locations of the top-level edges of a trace forest; we use
+/// these as the roots of the DCG for the traced computation.
+pub fn roots_of_traces(traces:&Vec<trace::Trace>) -> Vec<Loc> {
+  traces.iter().map(|tr| tr.edge.succ.loc.clone()).collect()
+}
+
+/// The locations of the top-level force edges of a trace forest: the
+/// roots that the traced computation demanded (unlike, e.g., thunks
+/// that it allocated, but never forced).
+pub fn force_roots_of_traces(traces:&Vec<trace::Trace>) -> Vec<Loc> {
+  traces.iter().filter(|tr| match tr.effect {
+    trace::Effect::Force(_) => true,
+    _ => false,
+  }).map(|tr| tr.edge.succ.loc.clone()).collect()
+}
+
+fn check_dcg_cycles(dcg:&DCG, on_stack:&mut Vec<Loc>, visited:&mut HashMap<Loc, ()>, 
+                    loc:&Loc, diags:&mut Vec<DcgDiagnostic>) {
+  if let Some(i) = on_stack.iter().position(|l| l == loc) {
+    diags.push(DcgDiagnostic::Cycle(on_stack[i..].to_vec()));
+    return
+  };
+  if visited.contains_key(loc) { return };
+  visited.insert(loc.clone(), ());
+  if let Some(succs) = dcg.table.get(loc).and_then(succs_of_node) {
+    on_stack.push(loc.clone());
+    for succ in succs.iter() {
+      check_dcg_cycles(dcg, on_stack, visited, &succ.loc, diags)
+    };
+    on_stack.pop();
+  }
+}
+
+fn check_dcg_dirty(dcg:&DCG, visited:&mut HashMap<Loc, ()>, 
+                   loc:&Loc, diags:&mut Vec<DcgDiagnostic>) {
+  if visited.contains_key(loc) { return };
+  visited.insert(loc.clone(), ());
+  if let Some(succs) = dcg.table.get(loc).and_then(succs_of_node) {
+    for succ in succs.iter().filter(|succ| succ.effect == Effect::Force) {
+      if succ.dirty {
+        diags.push(DcgDiagnostic::DirtyAfterUpdate(loc.clone(), succ.loc.clone()))
+      } else {
+        check_dcg_dirty(dcg, visited, &succ.loc, diags)
+      }
+    }
+  }
+}
+
+/// Checks the well-formedness of a reflected DCG: Every successor
+/// edge targets a node in the table; every computation that a clean
+/// edge forces has a value; and there are no cycles.  When
+/// `after_update` holds, also checks that no dirty force edge is
+/// reachable from the given roots (e.g., those of
+/// `force_roots_of_traces`), via clean force edges; elsewhere, e.g.,
+/// within thunks that are allocated but never forced, as in lazy
+/// labs, dirty edges may legitimately remain.  We visit locations in order of their
+/// strings, so that the diagnostics are deterministic.
+pub fn check_dcg(dcg:&DCG, roots:&Vec<Loc>, after_update:bool) -> Vec<DcgDiagnostic> {
+  let mut diags = vec![];
+  let mut locs : Vec<&Loc> = dcg.table.keys().collect();
+  locs.sort_by_key(|l| string_of_loc(l));
+  for loc in locs.iter() {
+    if let Some(succs) = dcg.table.get(*loc).and_then(succs_of_node) {
+      for succ in succs.iter() {
+        match dcg.table.get(&succ.loc) {
+          None => diags.push(DcgDiagnostic::DanglingSucc((*loc).clone(), succ.loc.clone())),
+          Some(&Node::Comp(ref nd)) => 
+            if succ.effect == Effect::Force && !succ.dirty && nd.value.is_none() {
+              diags.push(DcgDiagnostic::ForcedWithoutValue(succ.loc.clone()))
+            },
+          Some(_) => (),
+        }
+      }
+    }
+  };
+  let mut visited = HashMap::new();
+  for loc in locs.iter() {
+    check_dcg_cycles(dcg, &mut vec![], &mut visited, loc, &mut diags)
+  };
+  if after_update {
+    let mut visited = HashMap::new();
+    for root in roots.iter() {
+      check_dcg_dirty(dcg, &mut visited, root, &mut diags)
+    }
+  };
+  diags
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lab_params_defaults;
+  use labreg::LabRegistry;
+  use adapton::engine::name_of_str;
+
+  /// A lazy lab leaves dirty edges within the thunks that it
+  /// allocates, but does not force; these are not diagnostics.
+  #[test]
+  fn lazy_map_is_clean_after_update() {
+    let registry = LabRegistry::catalog();
+    let lab = registry.labs().iter().find(|l| l.name() == name_of_str("list-lazy-map")).unwrap();
+    let results = lab.run(&lab_params_defaults());
+    assert_eq!( results.errors.len(), 0 );
+    for sample in results.samples.iter() {
+      let s = sample.engine_sample(&EngineConfig::DCG).unwrap();
+      for diag in s.compute_output.dcg_diagnostics.iter() {
+        match *diag {
+          DcgDiagnostic::DirtyAfterUpdate(_, _) => panic!("batch {}: {}", sample.batch_name, diag.descr()),
+          _ => (),
+        }
+      }
+    }
+  }
+
+  /// Runs that end differently diverge, even when the samples that
+  /// they share agree.
+  #[test]
+  fn divergence_of_errors_and_stops() {
+    let registry = LabRegistry::catalog();
+    let lab = registry.labs().iter().find(|l| l.name() == name_of_str("list-eager-map")).unwrap();
+    let mut params = lab_params_defaults();
+    params.change_batch_loopc = 2;
+    let r1 = lab.run(&params);
+    assert!( results_divergence(&r1, &r1.clone()).is_none() );
+
+    let mut r2 = r1.clone();
+    r2.samples.pop();
+    r2.errors.push(LabError{ batch_name: 2, engine: EngineConfig::DCG,
+                             error: EditError::Impossible(String::from("test")) });
+    match results_divergence(&r1, &r2) {
+      Some(Divergence{ batch_name: 2, engine: None, kind: DivergenceKind::Errors(ref e1, ref e2) }) =>
+        assert_eq!( (e1.len(), e2.len()), (0, 1) ),
+      d => panic!("expected divergent errors, not {:?}", d),
+    };
+
+    let mut r3 = r1.clone();
+    r3.samples.pop();
+    r3.stopped = Some(String::from("time budget of 0 ms exceeded"));
+    match results_divergence(&r1, &r3) {
+      Some(Divergence{ batch_name: 2, engine: None, kind: DivergenceKind::Stopped(None, Some(_)) }) => (),
+      d => panic!("expected divergent stops, not {:?}", d),
+    }
+  }
+}
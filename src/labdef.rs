@@ -1,13 +1,22 @@
+use adapton::collections::{List, Tree, Dir2, vec_of_list, list_of_tree};
 use adapton::engine::Cnt; // Counters for engine costs
 use adapton::engine::Name; // Names, for naming things uniquely
 use adapton::engine::reflect::DCG;
 use adapton::engine::reflect::Val;
 use adapton::engine::reflect::trace::Trace;
+use labcheck::DcgDiagnostic;
+use labfit::Complexity;
+use labobs::Observer;
+use labprog::{LabProgress, NoProgress};
+use labstats::EffectCnt;
 use rand::Rng;
+use std::any::Any;
+use std::fmt::Debug;
+use std::hash::Hash;
 use std::marker::PhantomData;
 
 /// A bit that controls how names are placed in the input; See `README.md` for more.
-#[derive(Clone,Debug)]
+#[derive(Clone,Debug,PartialEq,Eq)]
 pub enum NominalStrategy {
   Regular,
   ByContent,
@@ -21,17 +30,43 @@ pub struct GenerateParams {
   pub nominal_strategy:NominalStrategy
 }
 
+/// A failure to generate or edit an input (See `Generate` and
+/// `Edit`); the runner records these in `LabResults::errors`.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum EditError {
+  /// The parameters are invalid for this editor, e.g., `gauge == 0`.
+  InvalidParams(String),
+  /// The editor does not support this nominal strategy.
+  UnsupportedStrategy(NominalStrategy),
+  /// The edit is impossible, e.g., focusing beyond the end of a sequence.
+  Impossible(String),
+  /// The editor is not implemented, yet.
+  Unimplemented,
+}
+
+impl EditError {
+  /// A short, human-readable description, e.g., for generated output.
+  pub fn descr(self:&Self) -> String {
+    match *self {
+      EditError::InvalidParams(ref msg)      => format!("Invalid parameters: {}", msg),
+      EditError::UnsupportedStrategy(ref st) => format!("Unsupported nominal strategy: {:?}", st),
+      EditError::Impossible(ref msg)         => format!("Impossible edit: {}", msg),
+      EditError::Unimplemented               => format!("Unimplemented editor"),
+    }
+  }
+}
+
 /// Generic method for generating a random input.
 /// See `README.md` for more.
 pub trait Generate<T> {
-  fn generate<R:Rng>(rng:&mut R, params:&GenerateParams) -> T;
+  fn generate<R:Rng>(rng:&mut R, params:&GenerateParams) -> Result<T, EditError>;
 } 
 
 /// Generic process for editing an input randomly, in a stateful sequence of edits.
 /// See `README.md` for more.
 pub trait Edit<T,S> : Clone {
   fn edit_init<R:Rng>(rng:&mut R, params:&GenerateParams) -> S;
-  fn edit<R:Rng>(pre_edit:T, edit_state:S, rng:&mut R, params:&GenerateParams) -> (T, S);
+  fn edit<R:Rng>(pre_edit:T, edit_state:S, rng:&mut R, params:&GenerateParams) -> Result<(T, S), EditError>;
 }
 
 /// Generic notion of a computation to run naively and incrementally.
@@ -53,24 +88,513 @@ impl<Input,Output,X:Compute<Input,Output>> ComputeDemand<Input,Output> for X {
   }
 }
 
+/// Lab-specific parameters ("knobs"), by name, e.g., the modulus of
+/// a filter's predicate.  Each knob has a default, given where the
+/// computation reads it (See `get`); so, one lab definition can be
+/// instantiated with several configurations, each setting some knobs.
+#[derive(Clone,Debug,Default,PartialEq,Eq)]
+pub struct LabKnobs {
+  pub knobs: Vec<(String, usize)>,
+}
+
+impl LabKnobs {
+  /// The value of the named knob, or the default, if not set.
+  pub fn get(self:&Self, name:&str, default:usize) -> usize {
+    match self.knobs.iter().find(|k| k.0 == name) {
+      Some(k) => k.1,
+      None => default,
+    }
+  }
+  /// Sets the named knob, replacing its prior value (if any).
+  pub fn set(self, name:&str, val:usize) -> Self {
+    let mut knobs : Vec<(String, usize)> = self.knobs.into_iter().filter(|k| k.0 != name).collect();
+    knobs.push((String::from(name), val));
+    LabKnobs{ knobs }
+  }
+}
+
+/// What a computation may consult, beyond its input: the parameters
+/// of the current sample, and the lab-specific knobs.
+#[derive(Clone,Debug)]
+pub struct ComputeContext<'a> {
+  pub sample_params: &'a SampleParams,
+  pub knobs:         &'a LabKnobs,
+}
+
+/// Like ComputeDemand, but provides the full context of the
+/// computation (See `ComputeContext`), e.g., for computations with
+/// knobs.
+pub trait ComputeCtx<Input,Output> {
+  fn compute(Input, &ComputeContext) -> Output;
+}
+
+impl<Input,Output,X:ComputeDemand<Input,Output>> ComputeCtx<Input,Output> for X {
+  fn compute(inp:Input, ctx:&ComputeContext) -> Output {
+    X::compute(inp, ctx.sample_params.demand)
+  }
+}
+
+/// Output equivalence: How to validate the output of each engine
+/// against that of the reference engine (See
+/// `LabParams::reference_engine`).  We compare _views_ of the
+/// outputs.  The runner takes each view of the measured output, but
+/// for views that force the output (See `view_forces`).
+pub trait OutputEquiv<Output> {
+  type View:'static;
+  fn view(output:&Output) -> Self::View;
+  fn equiv(v1:&Self::View, v2:&Self::View) -> bool;
+  /// Whether the view may force (lazy parts of) the output.  If so,
+  /// the runner takes the view in a shadow of the engine (See
+  /// `labrun::LabShadowState`), rather than perturb the measured DCG;
+  /// it then validates the shadow's output, not the measured output.
+  fn view_forces() -> bool { true }
+}
+
+/// The default output equivalence: equality of the outputs themselves
+/// (i.e., of their representations, including names and articulations).
+#[derive(Clone,Debug)]
+pub struct StructEq { }
+
+impl<Output:'static+Clone+Eq> OutputEquiv<Output> for StructEq {
+  type View = Output;
+  fn view(output:&Output) -> Output { output.clone() }
+  fn equiv(v1:&Output, v2:&Output) -> bool { v1 == v2 }
+  fn view_forces() -> bool { false }
+}
+
+/// Output equivalence by sequence content: equality of the elements,
+/// in order, ignoring names, articulations and (for trees) shape.
+#[derive(Clone,Debug)]
+pub struct SeqContent { }
+
+impl<X:'static+Clone+Debug+Eq+Hash> OutputEquiv<List<X>> for SeqContent {
+  type View = Vec<X>;
+  fn view(output:&List<X>) -> Vec<X> { vec_of_list(output.clone(), None) }
+  fn equiv(v1:&Vec<X>, v2:&Vec<X>) -> bool { v1 == v2 }
+}
+
+impl<X:'static+Clone+Debug+Eq+Hash> OutputEquiv<Tree<X>> for SeqContent {
+  type View = Vec<X>;
+  fn view(output:&Tree<X>) -> Vec<X> {
+    let list : List<X> = list_of_tree(output.clone(), Dir2::Left);
+    vec_of_list(list, None)
+  }
+  fn equiv(v1:&Vec<X>, v2:&Vec<X>) -> bool { v1 == v2 }
+}
+
+/// Output equivalence by set content: equality of the sets of
+/// elements, ignoring their order and multiplicity.
+#[derive(Clone,Debug)]
+pub struct SetContent { }
+
+impl<X:'static+Clone+Debug+Ord+Hash> OutputEquiv<List<X>> for SetContent {
+  type View = Vec<X>;
+  fn view(output:&List<X>) -> Vec<X> {
+    let mut elms = vec_of_list(output.clone(), None);
+    elms.sort();
+    elms.dedup();
+    elms
+  }
+  fn equiv(v1:&Vec<X>, v2:&Vec<X>) -> bool { v1 == v2 }
+}
+
+/// Output equivalence up to a relative numeric tolerance (of
+/// `APPROX_TOLERANCE`), e.g., for outputs that sum floating-point
+/// numbers in different orders.
+#[derive(Clone,Debug)]
+pub struct ApproxEq { }
+
+pub const APPROX_TOLERANCE : f64 = 1e-9;
+
+impl OutputEquiv<f64> for ApproxEq {
+  type View = f64;
+  fn view(output:&f64) -> f64 { *output }
+  fn equiv(v1:&f64, v2:&f64) -> bool {
+    let scale = if v1.abs() > v2.abs() { v1.abs() } else { v2.abs() };
+    (v1 - v2).abs() <= APPROX_TOLERANCE * if scale > 1.0 { scale } else { 1.0 }
+  }
+  fn view_forces() -> bool { false }
+}
+
+/// Output equivalence by both of two equivalences, e.g., by structure
+/// and by content.
+#[derive(Clone,Debug)]
+pub struct Both<E1,E2> { e1:PhantomData<E1>, e2:PhantomData<E2> }
+
+impl<Output,E1:OutputEquiv<Output>,E2:OutputEquiv<Output>> OutputEquiv<Output> for Both<E1,E2> {
+  type View = (E1::View, E2::View);
+  fn view(output:&Output) -> Self::View { (E1::view(output), E2::view(output)) }
+  fn equiv(v1:&Self::View, v2:&Self::View) -> bool {
+    E1::equiv(&v1.0, &v2.0) && E2::equiv(&v1.1, &v2.1)
+  }
+  fn view_forces() -> bool { E1::view_forces() || E2::view_forces() }
+}
+
+/// A tag that classifies a lab, e.g., for filtering which labs to run.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum LabTag {
+  List,
+  Tree,
+  Sort,
+  /// The computation is demand-driven (See `SampleParams::demand`).
+  Lazy,
+  /// The computation produces all of its output.
+  Eager,
+  /// Any other tag, e.g., for labs defined outside of our catalog.
+  Named(&'static str),
+}
+
+impl LabTag {
+  pub fn all() -> Vec<LabTag> {
+    vec![ LabTag::List, LabTag::Tree, LabTag::Sort, LabTag::Lazy, LabTag::Eager ]
+  }
+  pub fn name(self:&Self) -> &'static str {
+    match *self {
+      LabTag::List     => "list",
+      LabTag::Tree     => "tree",
+      LabTag::Sort     => "sort",
+      LabTag::Lazy     => "lazy",
+      LabTag::Eager    => "eager",
+      LabTag::Named(n) => n,
+    }
+  }
+}
+
+/// Descriptive metadata of a lab, for generated output (See
+/// `labviz::write_catalog_doc`) and for choosing which labs to run
+/// (See `labreg::LabRegistry::retain_tagged`).
+#[derive(Clone,Debug,Default)]
+pub struct LabMeta {
+  /// A human-readable description, in a sentence or two.
+  pub descr:             String,
+  pub tags:              Vec<LabTag>,
+  /// The names of the input and output types; `labdef!` fills these in.
+  pub input_type:        String,
+  pub output_type:       String,
+  /// Whether the computation honors `SampleParams::demand`, i.e.,
+  /// whether it only forces a prefix of its (lazy) output.
+  pub honors_demand:     bool,
+  /// The expected complexity of the update after each change batch,
+  /// as a function of input size; None if unknown.  Compare to the
+  /// fitted complexity (See `labfit::fit_size_sweep`).
+  pub update_complexity: Option<Complexity>,
+}
+
+impl LabMeta {
+  pub fn has_tag(self:&Self, tag:&LabTag) -> bool {
+    self.tags.iter().any(|t| t == tag)
+  }
+}
+
 /// _lab definition_: generic notion of an incremental computation
 /// that can be evaluated and tested.  We instantiate this structure
 /// once for each test in our test suite.  We implement the `LabDef`
 /// trait generically for this structure.  See `catalog` module for
-/// example instances.
+/// example instances.  The `Equiv` type validates the output (See
+/// `OutputEquiv`).
 pub struct LabDef<Input,EditSt,Output,
                Editor:   Generate<Input>+Edit<Input,EditSt>,
-               Archivist:ComputeDemand<Input,Output>> 
+               Archivist:ComputeCtx<Input,Output>,
+               Equiv=StructEq> 
 {
   pub identity:  Name,
   pub url:       Option<String>,
+  pub meta:      LabMeta,
+  pub knobs:     LabKnobs,
+  /// Observations of the output, after each computation (See `labobs`).
+  pub observers: Vec<Observer<Output>>,
 
   pub editor:    PhantomData<Editor>,
   pub archivist: PhantomData<Archivist>,
 
   pub input:     PhantomData<Input>,
   pub editst:    PhantomData<EditSt>,
-  pub output:    PhantomData<Output>
+  pub output:    PhantomData<Output>,
+  pub equiv:     PhantomData<Equiv>,
+}
+
+impl<Input,EditSt,Output,
+     Editor:   Generate<Input>+Edit<Input,EditSt>,
+     Archivist:ComputeCtx<Input,Output>,
+     Equiv>
+  LabDef<Input,EditSt,Output,Editor,Archivist,Equiv> {
+    /// Sets a knob of this lab (See `LabKnobs`), e.g., after `labdef!`.
+    pub fn with_knob(self:Box<Self>, name:&str, val:usize) -> Box<Self> {
+      let mut def = *self;
+      def.knobs = def.knobs.set(name, val);
+      Box::new(def)
+    }
+    /// Adds observers of this lab's output (See `labobs`), e.g., after `labdef!`.
+    pub fn with_observers(self:Box<Self>, observers:Vec<Observer<Output>>) -> Box<Self> {
+      let mut def = *self;
+      def.observers.extend(observers.into_iter());
+      Box::new(def)
+    }
+    /// Validates this lab's output with the given equivalence (See
+    /// `OutputEquiv`), e.g., after `labdef!`.
+    pub fn with_equiv<E2:OutputEquiv<Output>>(self:Box<Self>) 
+                                              -> Box<LabDef<Input,EditSt,Output,Editor,Archivist,E2>> {
+      let def = *self;
+      Box::new(LabDef{
+        identity:  def.identity,
+        url:       def.url,
+        meta:      def.meta,
+        knobs:     def.knobs,
+        observers: def.observers,
+        editor:    PhantomData,
+        archivist: PhantomData,
+        input:     PhantomData,
+        editst:    PhantomData,
+        output:    PhantomData,
+        equiv:     PhantomData,
+      })
+    }
+  }
+
+/// The editor and archivist of a lab, as values: The runner (See
+/// `labrun`) uses this trait, rather than the `Generate`, `Edit` and
+/// `ComputeCtx` traits directly, so that labs may define these
+/// parts either as types (`LabDef`) or as closures (`LabFnDef`).
+pub trait LabFns<Input,EditSt,Output> {
+  fn generate(self:&Self, rng:&mut Rng, params:&GenerateParams) -> Result<Input, EditError>;
+  fn edit_init(self:&Self, rng:&mut Rng, params:&GenerateParams) -> EditSt;
+  fn edit(self:&Self, pre_edit:Input, edit_state:EditSt,
+          rng:&mut Rng, params:&GenerateParams) -> Result<(Input, EditSt), EditError>;
+  fn compute(self:&Self, input:Input, params:&SampleParams) -> Output;
+  /// The view of the output that determines its validity (See `OutputEquiv`).
+  fn output_view(self:&Self, output:&Output) -> Box<Any>;
+  /// Whether two views (See `output_view`) are equivalent.
+  fn output_equiv(self:&Self, v1:&Any, v2:&Any) -> bool;
+  /// Whether the view may force the output (See `OutputEquiv::view_forces`).
+  fn output_view_forces(self:&Self) -> bool;
+  /// The observers of the output, in order (See `labobs`).
+  fn observers(self:&Self) -> &Vec<Observer<Output>>;
+}
+
+/// Whether two type-erased views are equivalent, per `Equiv`; views
+/// of other types are never equivalent.
+fn output_equiv_of_any<Output,Equiv:OutputEquiv<Output>>(v1:&Any, v2:&Any) -> bool {
+  match (v1.downcast_ref::<Equiv::View>(), v2.downcast_ref::<Equiv::View>()) {
+    (Some(v1), Some(v2)) => Equiv::equiv(v1, v2),
+    _ => false,
+  }
+}
+
+impl<Input,EditSt,Output,
+     Editor:   Generate<Input>+Edit<Input,EditSt>,
+     Archivist:ComputeCtx<Input,Output>,
+     Equiv:    OutputEquiv<Output>>
+  LabFns<Input,EditSt,Output> for LabDef<Input,EditSt,Output,Editor,Archivist,Equiv> {
+    fn generate(self:&Self, rng:&mut Rng, params:&GenerateParams) -> Result<Input, EditError> {
+      let mut rng = rng;
+      Editor::generate(&mut rng, params)
+    }
+    fn edit_init(self:&Self, rng:&mut Rng, params:&GenerateParams) -> EditSt {
+      let mut rng = rng;
+      Editor::edit_init(&mut rng, params)
+    }
+    fn edit(self:&Self, pre_edit:Input, edit_state:EditSt,
+            rng:&mut Rng, params:&GenerateParams) -> Result<(Input, EditSt), EditError> {
+      let mut rng = rng;
+      Editor::edit(pre_edit, edit_state, &mut rng, params)
+    }
+    fn compute(self:&Self, input:Input, params:&SampleParams) -> Output {
+      Archivist::compute(input, &ComputeContext{ sample_params:params, knobs:&self.knobs })
+    }
+    fn output_view(self:&Self, output:&Output) -> Box<Any> {
+      Box::new(Equiv::view(output))
+    }
+    fn output_equiv(self:&Self, v1:&Any, v2:&Any) -> bool {
+      output_equiv_of_any::<Output,Equiv>(v1, v2)
+    }
+    fn output_view_forces(self:&Self) -> bool { Equiv::view_forces() }
+    fn observers(self:&Self) -> &Vec<Observer<Output>> { &self.observers }
+  }
+
+/// _lab function definition_: Like `LabDef`, but holds the editor
+/// and archivist as closures, rather than as (phantom) types.  Build
+/// these with a `LabBuilder`.
+pub struct LabFnDef<Input,EditSt,Output> {
+  pub identity:  Name,
+  pub url:       Option<String>,
+  pub meta:      LabMeta,
+  pub knobs:     LabKnobs,
+  pub generate:  Box<Fn(&mut Rng, &GenerateParams) -> Result<Input, EditError>>,
+  pub edit_init: Box<Fn(&mut Rng, &GenerateParams) -> EditSt>,
+  pub edit:      Box<Fn(Input, EditSt, &mut Rng, &GenerateParams) -> Result<(Input, EditSt), EditError>>,
+  pub compute:   Box<Fn(Input, &ComputeContext) -> Output>,
+  pub view:      Box<Fn(&Output) -> Box<Any>>,
+  pub equiv:     Box<Fn(&Any, &Any) -> bool>,
+  /// Whether `view` may force the output (See `OutputEquiv::view_forces`).
+  pub view_forces: bool,
+  pub observers: Vec<Observer<Output>>,
+}
+
+impl<Input,EditSt,Output> LabFns<Input,EditSt,Output> for LabFnDef<Input,EditSt,Output> {
+  fn generate(self:&Self, rng:&mut Rng, params:&GenerateParams) -> Result<Input, EditError> {
+    (self.generate)(rng, params)
+  }
+  fn edit_init(self:&Self, rng:&mut Rng, params:&GenerateParams) -> EditSt {
+    (self.edit_init)(rng, params)
+  }
+  fn edit(self:&Self, pre_edit:Input, edit_state:EditSt,
+          rng:&mut Rng, params:&GenerateParams) -> Result<(Input, EditSt), EditError> {
+    (self.edit)(pre_edit, edit_state, rng, params)
+  }
+  fn compute(self:&Self, input:Input, params:&SampleParams) -> Output {
+    (self.compute)(input, &ComputeContext{ sample_params:params, knobs:&self.knobs })
+  }
+  fn output_view(self:&Self, output:&Output) -> Box<Any> {
+    (self.view)(output)
+  }
+  fn output_equiv(self:&Self, v1:&Any, v2:&Any) -> bool {
+    (self.equiv)(v1, v2)
+  }
+  fn output_view_forces(self:&Self) -> bool { self.view_forces }
+  fn observers(self:&Self) -> &Vec<Observer<Output>> { &self.observers }
+}
+
+/// Builds a lab from values, without the `labdef!` macro, and
+/// without defining a type for each part: The generator, editor and
+/// computation are closures.  E.g.,
+/// `LabBuilder::new(name_of_str("my-lab")).generator(..).editor(..,..).compute(..).build()`.
+/// Each of `generator`, `editor` and `compute` (or `compute_demand`,
+/// or `compute_ctx`) is required; `build` panics if one is missing.
+pub struct LabBuilder<Input,EditSt,Output> {
+  identity:  Name,
+  url:       Option<String>,
+  meta:      LabMeta,
+  knobs:     LabKnobs,
+  generate:  Option<Box<Fn(&mut Rng, &GenerateParams) -> Result<Input, EditError>>>,
+  edit_init: Option<Box<Fn(&mut Rng, &GenerateParams) -> EditSt>>,
+  edit:      Option<Box<Fn(Input, EditSt, &mut Rng, &GenerateParams) -> Result<(Input, EditSt), EditError>>>,
+  compute:   Option<Box<Fn(Input, &ComputeContext) -> Output>>,
+  view:      Option<Box<Fn(&Output) -> Box<Any>>>,
+  equiv:     Option<Box<Fn(&Any, &Any) -> bool>>,
+  view_forces: bool,
+  observers: Vec<Observer<Output>>,
+}
+
+impl<Input:'static,EditSt:'static,Output:'static> LabBuilder<Input,EditSt,Output> {
+  pub fn new(identity:Name) -> Self {
+    LabBuilder{
+      identity,
+      url:       None,
+      meta:      LabMeta::default(),
+      knobs:     LabKnobs::default(),
+      generate:  None,
+      edit_init: None,
+      edit:      None,
+      compute:   None,
+      view:      None,
+      equiv:     None,
+      view_forces: true,
+      observers: vec![],
+    }
+  }
+
+  /// The URL to display in generated output (See `Lab::url`).
+  pub fn url(self, url:&str) -> Self {
+    LabBuilder{ url:Some(String::from(url)), .. self }
+  }
+
+  /// The descriptive metadata of the lab (See `LabMeta`); unlike
+  /// `labdef!`, the builder does not fill in the type names.
+  pub fn meta(self, meta:LabMeta) -> Self {
+    LabBuilder{ meta, .. self }
+  }
+
+  /// Sets a knob of the lab (See `LabKnobs`); the computation reads
+  /// it from its context (See `compute_ctx`).
+  pub fn knob(self, name:&str, val:usize) -> Self {
+    let knobs = self.knobs.clone().set(name, val);
+    LabBuilder{ knobs, .. self }
+  }
+
+  /// How to generate the initial input (See `Generate`).
+  pub fn generator<G>(self, generate:G) -> Self
+    where G:'static+Fn(&mut Rng, &GenerateParams) -> Result<Input, EditError>
+  {
+    LabBuilder{ generate:Some(Box::new(generate)), .. self }
+  }
+
+  /// How to edit the input, in a stateful sequence of edits (See `Edit`).
+  pub fn editor<I,E>(self, edit_init:I, edit:E) -> Self
+    where I:'static+Fn(&mut Rng, &GenerateParams) -> EditSt,
+          E:'static+Fn(Input, EditSt, &mut Rng, &GenerateParams) -> Result<(Input, EditSt), EditError>
+  {
+    LabBuilder{ edit_init:Some(Box::new(edit_init)), edit:Some(Box::new(edit)), .. self }
+  }
+
+  /// The computation, which ignores the demand (See `Compute`).
+  pub fn compute<C>(self, compute:C) -> Self
+    where C:'static+Fn(Input) -> Output
+  {
+    self.compute_demand(move |input, _demand| compute(input))
+  }
+
+  /// The computation, given the demand (See `ComputeDemand`).
+  pub fn compute_demand<C>(self, compute:C) -> Self
+    where C:'static+Fn(Input, usize) -> Output
+  {
+    self.compute_ctx(move |input, ctx| compute(input, ctx.sample_params.demand))
+  }
+
+  /// The computation, given its context (See `ComputeCtx`).
+  pub fn compute_ctx<C>(self, compute:C) -> Self
+    where C:'static+Fn(Input, &ComputeContext) -> Output
+  {
+    LabBuilder{ compute:Some(Box::new(compute)), .. self }
+  }
+
+  /// Adds an observer of the output (See `labobs`).
+  pub fn observer(self, observer:Observer<Output>) -> Self {
+    let mut builder = self;
+    builder.observers.push(observer);
+    builder
+  }
+
+  /// Validates the output with the given equivalence (See
+  /// `OutputEquiv`); `build` defaults to `StructEq`.
+  pub fn equiv<E:'static+OutputEquiv<Output>>(self) -> Self {
+    LabBuilder{
+      view:  Some(Box::new(|output:&Output| Box::new(E::view(output)) as Box<Any>)),
+      equiv: Some(Box::new(output_equiv_of_any::<Output,E>)),
+      view_forces: E::view_forces(),
+      .. self
+    }
+  }
+
+  /// The lab definition; panics if a part is missing, including the
+  /// output equivalence (See `equiv`).
+  pub fn build_def(self) -> LabFnDef<Input,EditSt,Output> {
+    let name = format!("{:?}", self.identity);
+    let missing = |part:&str| format!("LabBuilder {}: missing {}", name, part);
+    LabFnDef{
+      identity:  self.identity,
+      url:       self.url,
+      meta:      self.meta,
+      knobs:     self.knobs,
+      generate:  self.generate.unwrap_or_else(|| panic!("{}", missing("generator"))),
+      edit_init: self.edit_init.unwrap_or_else(|| panic!("{}", missing("editor"))),
+      edit:      self.edit.unwrap_or_else(|| panic!("{}", missing("editor"))),
+      compute:   self.compute.unwrap_or_else(|| panic!("{}", missing("compute"))),
+      view:      self.view.unwrap_or_else(|| panic!("{}", missing("equiv"))),
+      equiv:     self.equiv.unwrap_or_else(|| panic!("{}", missing("equiv"))),
+      view_forces: self.view_forces,
+      observers: self.observers,
+    }
+  }
+
+  /// The lab; panics if a part is missing.  Validates the output by
+  /// equality (`StructEq`), unless given another equivalence; for
+  /// outputs that are not `Eq`, use `equiv` and `build_def` instead.
+  pub fn build(self) -> Box<Lab>
+    where Input:Clone+Debug+Eq, Output:Clone+Eq+Debug
+  {
+    let builder = if self.equiv.is_none() { self.equiv::<StructEq>() } else { self };
+    Box::new(builder.build_def())
+  }
 }
 
 /// _lab_: Abstracts over parts of a lab definition of type `LabDef`:
@@ -78,7 +602,77 @@ pub struct LabDef<Input,EditSt,Output,
 pub trait Lab {
   fn name(self:&Self) -> Name;
   fn url(self:&Self) -> &Option<String>;
-  fn run(self:&Self, params:&LabParams) -> LabResults;
+  fn meta(self:&Self) -> &LabMeta;
+  fn knobs(self:&Self) -> &LabKnobs;
+  /// Runs the lab, giving each sample to the progress as soon as it
+  /// is collected; the progress may stop the run early (See
+  /// `labprog`).
+  fn run_with(self:&Self, params:&LabParams, progress:&mut LabProgress) -> LabResults;
+  /// Runs the lab, to its last batch.
+  fn run(self:&Self, params:&LabParams) -> LabResults {
+    self.run_with(params, &mut NoProgress)
+  }
+}
+
+/// An engine configuration: a choice of engine, and how the lab
+/// uses it across successive samples.  The lab samples each
+/// configuration listed in `LabParams::engines`, in order.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum EngineConfig {
+  /// The naive engine: No caching; recomputes everything, every sample.
+  Naive,
+  /// The DCG engine: One DCG, reused (via change propagation) across samples.
+  DCG,
+  /// The DCG engine, from scratch: A fresh, empty DCG for each
+  /// sample, into which we regenerate the current input by replaying
+  /// the edits of all prior change batches.
+  DCGFromScratch,
+  /// Like `DCG`, but never reflects the trace or the DCG, regardless
+  /// of `SampleParams`; useful for timing without reflection overhead.
+  DCGNoReflect,
+}
+
+impl EngineConfig {
+  /// A short name, e.g., for column headings in generated output.
+  pub fn name(self:&Self) -> &'static str {
+    match *self {
+      EngineConfig::Naive          => "Naive",
+      EngineConfig::DCG            => "DCG",
+      EngineConfig::DCGFromScratch => "DCG-from-scratch",
+      EngineConfig::DCGNoReflect   => "DCG-no-reflect",
+    }
+  }
+}
+
+/// A named choice of random number generator, for generating and
+/// editing inputs, and for the observers.  Each names one algorithm,
+/// implemented and seeded within this crate (See `labrng`), so that
+/// its raw stream of words is fixed by `SampleParams::input_seeds`.
+/// (The editors map these words to values through `rand::Rng`, e.g.,
+/// `gen` and `gen_range`, whose mappings belong to `rand` 0.3; and
+/// `usize` values depend on the target's word size.  Hence, a lab's
+/// inputs are reproducible with the same `rand` and target.)
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum LabRng {
+  /// PCG32 (See `labrng::Pcg32`).
+  Pcg32,
+  /// Xorshift64* (See `labrng::XorShift64Star`); fast, but of lower
+  /// quality.
+  XorShift64Star,
+}
+
+impl LabRng {
+  /// Every choice, e.g., for validating command-line arguments.
+  pub fn all() -> Vec<LabRng> {
+    vec![ LabRng::Pcg32, LabRng::XorShift64Star ]
+  }
+  /// A short name, e.g., for command-line arguments and generated output.
+  pub fn name(self:&Self) -> &'static str {
+    match *self {
+      LabRng::Pcg32          => "pcg32",
+      LabRng::XorShift64Star => "xorshift64star",
+    }
+  }
 }
 
 /// Parameters to running a single lab experiment.
@@ -88,6 +682,30 @@ pub struct LabParams {
   // TODO: Pretty-print input and output structures; graphmovie dump of experiment
   /// Number of change-batches to perform in a loop; each is interposed with computing the new output.
   pub change_batch_loopc: usize,
+  /// The engine configurations to sample, in order; each sample
+  /// holds one `EngineSample` per configuration, in this order.
+  pub engines: Vec<EngineConfig>,
+  /// The engine configuration whose output is the reference for
+  /// validation; it should appear in `engines`.
+  pub reference_engine: EngineConfig,
+  /// When set, some batches change only the demand, and leave the
+  /// input unchanged (See `DemandChange`).
+  pub demand_change: Option<DemandChange>,
+  /// The random number generator, seeded by `SampleParams::input_seeds`.
+  pub rng: LabRng,
+}
+
+/// A lab mode in which some batches change what the computation
+/// observes (its demand, and the choices of its observers; See
+/// `labobs`), rather than editing the input.  This measures the DCG's
+/// reuse when the same input is explored through different demands.
+#[derive(Clone,Debug)]
+pub struct DemandChange {
+  /// Every `period`th batch (after the first) changes only the demand.
+  pub period: usize,
+  /// Each such batch chooses a new demand, uniformly, from zero to
+  /// this maximum (inclusive); it holds until the next such batch.
+  pub max_demand: usize,
 }
 
 /// Parameters for collecting a single sample.  In addition to these
@@ -110,6 +728,10 @@ pub struct SampleParams {
   pub demand: usize,
   /// Whether to validate the output after each computation using the naive and DCG engines
   pub validate_output:   bool,
+  /// Whether to validate the input after each edit, across the
+  /// engines; distinguishes nondeterministic (or engine-dependent)
+  /// editing from inconsistent computing.
+  pub validate_input:    bool,
   /// Size of each batch of changes.
   pub change_batch_size: usize,
   /// Reflect the trace (See `adapton::engine::reflect::trace::Trace`).
@@ -122,27 +744,84 @@ pub struct SampleParams {
 /// The result of a lab is a sequence of samples.
 #[derive(Clone,Debug)]
 pub struct LabResults {
-  pub samples: Vec<Sample>
+  /// The random number generator of the lab (See `LabParams::rng`).
+  pub rng:     LabRng,
+  pub samples: Vec<Sample>,
+  /// The failures of the lab's editor, if any (See `EditError`); the
+  /// lab stops at its first failure, so there is at most one.
+  pub errors:  Vec<LabError>,
+  /// Why the run stopped before its last batch, if its progress
+  /// stopped it (See `labprog::Flow`).
+  pub stopped: Option<String>,
+}
+
+/// A failure of a lab's editor (See `EditError`), in the given batch
+/// and engine.  The samples of the lab end before this batch: when
+/// the k-th engine of `LabParams::engines` fails, the runner drops
+/// the metrics that it already took from the engines before it, in
+/// this batch, since the batch cannot be compared across engines.
+#[derive(Clone,Debug)]
+pub struct LabError {
+  pub batch_name: usize,
+  pub engine:     EngineConfig,
+  pub error:      EditError,
 }
 
 /// The experiment consists of a loop over samples.  For each sample,
-/// we switch back and forth between using the Naive engine, and using
-/// the DCG engine.  We want to interleave this way for each sample in
-/// order to compare outputs and metrics (counts and timings) on a
-/// fine-grained scale.
+/// we switch back and forth between the engine configurations of
+/// `LabParams::engines`.  We want to interleave this way for each
+/// sample in order to compare outputs and metrics (counts and
+/// timings) on a fine-grained scale.  A sample is complete: when an
+/// engine fails in a batch (See `LabError`), there is no sample for
+/// that batch, even from the engines that preceded it.
 #[derive(Clone,Debug)]
 pub struct Sample {
   //pub params:       SampleParams,
-  pub batch_name:   usize,   // Index/name the change batches; one sample per compute + change batch
-  pub dcg_sample:   EngineSample,
-  pub naive_sample: EngineSample,
-  pub output_valid: Option<bool>
+  pub batch_name:     usize,   // Index/name the change batches; one sample per compute + change batch
+  /// One sample per engine configuration, in the order of `LabParams::engines`.
+  pub engine_samples: Vec<EngineSample>,
+  /// Whether every engine's input matches that of the reference engine.
+  pub input_valid:    Option<bool>,
+  /// Whether every engine's output matches that of the reference engine.
+  pub output_valid:   Option<bool>,
+  /// Whether this batch left the input unchanged, and changed only
+  /// the demand (See `LabParams::demand_change`).
+  pub demand_only:    bool,
+  /// The demand of this batch (See `SampleParams::demand`).
+  pub demand:         usize,
+}
+
+impl Sample {
+  /// The sample of the given engine configuration, if any.
+  pub fn engine_sample(self:&Self, config:&EngineConfig) -> Option<&EngineSample> {
+    self.engine_samples.iter().find(|s| s.engine == *config)
+  }
+}
+
+/// The two roles of each engine sample: the _editor_ processes the
+/// input (left vertical edge in `README.md` diagram), and the
+/// _archivist_ computes the output over this input.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum Role {
+  Editor,
+  Archivist,
+}
+
+impl Role {
+  pub fn name(self:&Self) -> &'static str {
+    match *self {
+      Role::Editor    => "editor",
+      Role::Archivist => "archivist",
+    }
+  }
 }
 
 /// To sample a single engine, we record metrics for processing the
 /// input (left vertical edge in `README.md` diagram).
 #[derive(Clone,Debug)]
 pub struct EngineSample {
+  /// The engine configuration that produced this sample.
+  pub engine: EngineConfig,
   /// TODO: Rename: 'editor' (for editor role)
   pub process_input:    EngineMetrics,
   /// TODO: Rename: 'archivist' (for archivist role)
@@ -150,6 +829,43 @@ pub struct EngineSample {
   
   pub input: Option<Val>,
   pub output: Option<Val>,
+  /// Whether this engine's edited input matches that of the reference
+  /// engine (See `LabParams::reference_engine`).
+  pub input_valid: Option<bool>,
+  /// Whether this engine's output matches that of the reference
+  /// engine (See `LabParams::reference_engine`).  When the view of
+  /// the output may force it (See `OutputEquiv::view_forces`), every
+  /// engine but the naive one instead compares the output of a
+  /// shadow of the engine, which follows the same edits and
+  /// computations (See `labrun::LabShadowState`).
+  pub output_valid: Option<bool>,
+  /// One observation per observer of the lab, in order (See `labobs`).
+  pub observations: Vec<Observation>,
+  /// From scratch (See `EngineConfig::DCGFromScratch`), the time to
+  /// regenerate the input and replay the edits of prior batches;
+  /// `process_input` holds only the edit of this batch.
+  pub replay_time_ns: Option<u64>,
+}
+
+/// The sample of one observer (See `labobs::Observer`), after one
+/// computation.
+#[derive(Clone,Debug)]
+pub struct Observation {
+  pub name:    String,
+  pub metrics: EngineMetrics,
+  /// Whether this observation matches that of the reference engine
+  /// (See `LabParams::reference_engine`).
+  pub valid:   Option<bool>,
+}
+
+impl EngineSample {
+  /// The metrics of the given role.
+  pub fn metrics(self:&Self, role:&Role) -> &EngineMetrics {
+    match *role {
+      Role::Editor    => &self.process_input,
+      Role::Archivist => &self.compute_output,
+    }
+  }
 }
 
 /// For each engine, for each sampled subcomputation, we record the
@@ -161,10 +877,16 @@ pub struct EngineMetrics {
   /// Counts of engine (engine trace-level) operations (See
   /// `engine::reflect::trace::Trace`) during the sample.
   pub engine_cnt: Cnt,
+  /// Counts of each effect kind in `reflect_traces` (See
+  /// `labstats::EffectCnt`); all zeros if traces are not reflected.
+  pub effect_cnt: EffectCnt,
   /// The reflected traces of the DCG during the sample; empty if this
   /// option is disabled.
   pub reflect_traces: Vec<Trace>,
   /// The reflected version of the DCG, at the conclusion of the
   /// sample; None if this option is disabled.
   pub reflect_dcg: Option<DCG>,
+  /// Well-formedness diagnostics for the reflected DCG (See
+  /// `labcheck::check_dcg`); empty if the DCG is not reflected.
+  pub dcg_diagnostics: Vec<DcgDiagnostic>,
 }
@@ -0,0 +1,401 @@
+use labdef::*;
+
+/// A candidate asymptotic complexity, as a function of input size `n`.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum Complexity {
+  Const,
+  Log,
+  LogSquared,
+  Linear,
+  NLogN,
+  Quadratic,
+}
+
+impl Complexity {
+  pub fn all() -> Vec<Complexity> {
+    vec![ Complexity::Const, Complexity::Log, Complexity::LogSquared,
+          Complexity::Linear, Complexity::NLogN, Complexity::Quadratic ]
+  }
+  pub fn name(self:&Self) -> &'static str {
+    match *self {
+      Complexity::Const      => "O(1)",
+      Complexity::Log        => "O(log n)",
+      Complexity::LogSquared => "O(log² n)",
+      Complexity::Linear     => "O(n)",
+      Complexity::NLogN      => "O(n log n)",
+      Complexity::Quadratic  => "O(n²)",
+    }
+  }
+  /// The complexity function at size `n`.
+  pub fn eval(self:&Self, n:f64) -> f64 {
+    let log_n = if n > 1.0 { n.log2() } else { 1.0 };
+    match *self {
+      Complexity::Const      => 1.0,
+      Complexity::Log        => log_n,
+      Complexity::LogSquared => log_n * log_n,
+      Complexity::Linear     => n,
+      Complexity::NLogN      => n * log_n,
+      Complexity::Quadratic  => n * n,
+    }
+  }
+}
+
+/// A quantity that we measure for each lab, at each size.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum Measure {
+  /// Time of the naive engine's archivist, over all batches.
+  NaiveTime,
+  /// Time of the archivist, from scratch (See `EngineConfig::DCGFromScratch`).
+  FromScratchTime,
+  /// Time of the DCG engine's archivist, over updates (all batches but the first).
+  UpdateTime,
+  /// Engine counts of the DCG engine's archivist, over updates.
+  UpdateCnt,
+}
+
+impl Measure {
+  pub fn all() -> Vec<Measure> {
+    vec![ Measure::NaiveTime, Measure::FromScratchTime,
+          Measure::UpdateTime, Measure::UpdateCnt ]
+  }
+  pub fn name(self:&Self) -> &'static str {
+    match *self {
+      Measure::NaiveTime       => "naive time",
+      Measure::FromScratchTime => "DCG from-scratch time",
+      Measure::UpdateTime      => "DCG update time",
+      Measure::UpdateCnt       => "DCG update counts",
+    }
+  }
+  /// The mean of this measure over the samples of one lab result,
+  /// but those that change only the demand (See
+  /// `Sample::demand_only`), which edit nothing; None if the lab did
+  /// not sample the necessary engine.
+  pub fn mean(self:&Self, results:&LabResults) -> Option<f64> {
+    let (engine, skip) = match *self {
+      Measure::NaiveTime       => (EngineConfig::Naive, 0),
+      Measure::FromScratchTime => (EngineConfig::DCGFromScratch, 0),
+      Measure::UpdateTime      => (EngineConfig::DCG, 1),
+      Measure::UpdateCnt       => (EngineConfig::DCG, 1),
+    };
+    let xs : Vec<f64> = results.samples.iter().skip(skip).filter(|s| !s.demand_only).filter_map(|sample| {
+      sample.engine_sample(&engine).map(|s| {
+        let m = &s.compute_output;
+        match *self {
+          Measure::UpdateCnt =>
+            (m.engine_cnt.create + m.engine_cnt.eval +
+             m.engine_cnt.dirty + m.engine_cnt.clean) as f64,
+          _ => m.time_ns as f64,
+        }
+      })
+    }).collect();
+    if xs.len() == 0 { None } else { Some(xs.iter().sum::<f64>() / xs.len() as f64) }
+  }
+}
+
+/// Least-squares fit of `y = slope * x + intercept`; returns the
+/// slope, the intercept, and the coefficient of determination (r²).
+pub fn linear_fit(xs:&Vec<f64>, ys:&Vec<f64>) -> (f64, f64, f64) {
+  let n = xs.len() as f64;
+  let mean_x = xs.iter().sum::<f64>() / n;
+  let mean_y = ys.iter().sum::<f64>() / n;
+  let mut sxy = 0.0; let mut sxx = 0.0; let mut syy = 0.0;
+  for (x, y) in xs.iter().zip(ys.iter()) {
+    sxy += (x - mean_x) * (y - mean_y);
+    sxx += (x - mean_x) * (x - mean_x);
+    syy += (y - mean_y) * (y - mean_y);
+  };
+  let slope = if sxx == 0.0 { 0.0 } else { sxy / sxx };
+  let intercept = mean_y - slope * mean_x;
+  let r2 = if sxx == 0.0 || syy == 0.0 { 0.0 } else { (sxy * sxy) / (sxx * syy) };
+  (slope, intercept, r2)
+}
+
+/// The fit of a measure to one candidate complexity, `y = coeff * f(n)`.
+#[derive(Clone,Debug)]
+pub struct ModelFit {
+  pub complexity: Complexity,
+  pub coeff:      f64,
+  /// Coefficient of determination, `1 - SSE/SST`; at most 1, and
+  /// negative when the model fits worse than the mean.
+  pub r2:         f64,
+}
+
+/// For one lab and one measure, the fitted asymptotics over a sweep
+/// of sizes.
+#[derive(Clone,Debug)]
+pub struct ComplexityFit {
+  pub measure:   Measure,
+  /// The (size, mean measure) pairs that we fit.
+  pub points:    Vec<(usize, f64)>,
+  /// The slope of the log-log fit (e.g., near 1 for linear growth), and its r².
+  pub log_slope: f64,
+  pub log_r2:    f64,
+  /// Every candidate complexity, best fit first.
+  pub models:    Vec<ModelFit>,
+}
+
+impl ComplexityFit {
+  /// The best-fitting candidate complexity.
+  pub fn best(self:&Self) -> &ModelFit { &self.models[0] }
+}
+
+/// Fits the candidate complexities to the given (size, measure)
+/// points; None if there are fewer than two points, or if some
+/// measure is not positive.
+pub fn fit_complexity(measure:&Measure, points:Vec<(usize, f64)>) -> Option<ComplexityFit> {
+  if points.len() < 2 || points.iter().any(|&(n, y)| n == 0 || y <= 0.0) { return None };
+  let ln_ns : Vec<f64> = points.iter().map(|&(n, _)| (n as f64).ln()).collect();
+  let ln_ys : Vec<f64> = points.iter().map(|&(_, y)| y.ln()).collect();
+  let (log_slope, _, log_r2) = linear_fit(&ln_ns, &ln_ys);
+  let mean_y = points.iter().map(|&(_, y)| y).sum::<f64>() / points.len() as f64;
+  let sst = points.iter().fold(0.0, |s, &(_, y)| s + (y - mean_y) * (y - mean_y));
+  let mut models : Vec<ModelFit> = Complexity::all().into_iter().map(|complexity| {
+    let (sfy, sff) = points.iter().fold((0.0, 0.0), |(sfy, sff), &(n, y)| {
+      let f = complexity.eval(n as f64);
+      (sfy + f * y, sff + f * f)
+    });
+    let coeff = sfy / sff;
+    let sse = points.iter().fold(0.0, |s, &(n, y)| {
+      let e = y - coeff * complexity.eval(n as f64);
+      s + e * e
+    });
+    let r2 = if sst == 0.0 { if sse == 0.0 { 1.0 } else { 0.0 } } else { 1.0 - sse / sst };
+    ModelFit{ complexity, coeff, r2 }
+  }).collect();
+  models.sort_by(|m1, m2| m2.r2.partial_cmp(&m1.r2).unwrap());
+  Some(ComplexityFit{ measure:measure.clone(), points, log_slope, log_r2, models })
+}
+
+/// Runs the lab once for each size, without reflection (which would
+/// dominate the timings), and otherwise with the given parameters.
+pub fn run_size_sweep(lab:&Box<Lab>, params:&LabParams, sizes:&Vec<usize>) -> Vec<(usize, LabResults)> {
+  sizes.iter().map(|&size| {
+    let mut params = params.clone();
+    params.sample_params.generate_params.size = size;
+    params.sample_params.reflect_trace = false;
+    params.sample_params.reflect_dcg = false;
+    (size, lab.run(&params))
+  }).collect()
+}
+
+/// Fits each measure (See `Measure`) over the results of a size sweep.
+pub fn fit_size_sweep(sweep:&Vec<(usize, LabResults)>) -> Vec<ComplexityFit> {
+  Measure::all().iter().filter_map(|measure| {
+    let points = sweep.iter().filter_map(|&(size, ref results)| {
+      measure.mean(results).map(|y| (size, y))
+    }).collect();
+    fit_complexity(measure, points)
+  }).collect()
+}
+
+/// Solves the least-squares problem `rows * coeffs ≈ ys`, via the
+/// normal equations and Gaussian elimination; None if the system is
+/// singular (e.g., some counter is always zero, or two counters are
+/// proportional).  Since the entries of `XᵀX` scale with the square
+/// of the counts, we first scale each column to unit length, so that
+/// the pivot cutoff does not depend on the magnitude of the counts.
+pub fn least_squares(rows:&Vec<Vec<f64>>, ys:&Vec<f64>) -> Option<Vec<f64>> {
+  let k = match rows.first() { None => return None, Some(r) => r.len() };
+  let norms : Vec<f64> = (0..k).map(|j| rows.iter().fold(0.0, |s, row| s + row[j] * row[j]).sqrt()).collect();
+  if norms.iter().any(|n| *n == 0.0) { return None };
+  // Augmented matrix of the normal equations: [ XᵀX | Xᵀy ], over the scaled columns
+  let mut a = vec![ vec![0.0; k + 1]; k ];
+  for (row, y) in rows.iter().zip(ys.iter()) {
+    for i in 0..k {
+      for j in 0..k { a[i][j] += (row[i] / norms[i]) * (row[j] / norms[j]) };
+      a[i][k] += (row[i] / norms[i]) * y;
+    }
+  };
+  for col in 0..k {
+    let pivot = (col..k).fold(col, |p, r| if a[r][col].abs() > a[p][col].abs() { r } else { p });
+    if a[pivot][col].abs() < 1e-10 { return None };
+    a.swap(col, pivot);
+    for r in 0..k {
+      if r == col { continue };
+      let factor = a[r][col] / a[col][col];
+      for c in col..(k + 1) {
+        let x = a[col][c];
+        a[r][c] -= factor * x;
+      }
+    }
+  };
+  Some((0..k).map(|i| a[i][k] / a[i][i] / norms[i]).collect())
+}
+
+/// Like `least_squares`, but drops the columns that are zero in every
+/// row (e.g., a counter that no sample increments), whose
+/// coefficients are then zero.
+pub fn least_squares_nonzero(rows:&Vec<Vec<f64>>, ys:&Vec<f64>) -> Option<Vec<f64>> {
+  let k = match rows.first() { None => return None, Some(r) => r.len() };
+  let cols : Vec<usize> = (0..k).filter(|&j| rows.iter().any(|row| row[j] != 0.0)).collect();
+  let sub_rows : Vec<Vec<f64>> = rows.iter().map(|row| cols.iter().map(|&j| row[j]).collect()).collect();
+  let sub_cs = match least_squares(&sub_rows, ys) { None => return None, Some(cs) => cs };
+  let mut cs = vec![ 0.0; k ];
+  for (&j, c) in cols.iter().zip(sub_cs.into_iter()) { cs[j] = c };
+  Some(cs)
+}
+
+/// The counters of `Cnt` that the cost model uses, as regressors.
+fn cost_features(m:&EngineMetrics) -> Vec<f64> {
+  vec![ 1.0,
+        m.engine_cnt.dirty  as f64,
+        m.engine_cnt.clean  as f64,
+        m.engine_cnt.eval   as f64,
+        m.engine_cnt.create as f64 ]
+}
+
+/// A linear model of wall time (in nanoseconds) against engine
+/// counters: `time_ns ≈ fixed + dirty * cnt.dirty + clean * cnt.clean + ...`.
+#[derive(Clone,Debug)]
+pub struct CostModel {
+  pub fixed:  f64,
+  pub dirty:  f64,
+  pub clean:  f64,
+  pub eval:   f64,
+  pub create: f64,
+  /// Coefficient of determination, over all samples.
+  pub r2:     f64,
+}
+
+impl CostModel {
+  pub fn predict(self:&Self, m:&EngineMetrics) -> f64 {
+    let cs = vec![ self.fixed, self.dirty, self.clean, self.eval, self.create ];
+    cost_features(m).iter().zip(cs.iter()).fold(0.0, |s, (f, c)| s + f * c)
+  }
+}
+
+/// The residuals of one lab under a `CostModel`.
+#[derive(Clone,Debug)]
+pub struct CostResidual {
+  pub samples:  usize,
+  /// Mean of `time_ns - predicted`; positive when the lab does work
+  /// that the counters do not capture.
+  pub mean:     f64,
+  /// Mean of `|time_ns - predicted| / time_ns`.
+  pub mean_rel: f64,
+}
+
+/// Cost-model calibration: Fits a `CostModel` to the editor and
+/// archivist metrics of the given engine, across all samples of all
+/// labs.  Returns the model, and the residuals of each lab, in order;
+/// None if there are too few samples, or the counters are degenerate.
+/// A counter that is zero in every sample gets a zero coefficient.
+/// Use an engine without reflection (e.g., `DCGNoReflect`), since
+/// reflection costs time that no counter captures.
+pub fn fit_cost_model(results:&Vec<LabResults>, engine:&EngineConfig) -> Option<(CostModel, Vec<CostResidual>)> {
+  let metrics : Vec<Vec<&EngineMetrics>> = results.iter().map(|r| {
+    let mut ms = vec![];
+    for sample in r.samples.iter() {
+      if let Some(s) = sample.engine_sample(engine) {
+        ms.push(&s.process_input);
+        ms.push(&s.compute_output);
+      }
+    };
+    ms
+  }).collect();
+  let rows : Vec<Vec<f64>> = metrics.iter().flat_map(|ms| ms.iter().map(|m| cost_features(m))).collect();
+  let ys   : Vec<f64> = metrics.iter().flat_map(|ms| ms.iter().map(|m| m.time_ns as f64)).collect();
+  if rows.len() < 5 { return None };
+  let cs = match least_squares_nonzero(&rows, &ys) { None => return None, Some(cs) => cs };
+  let model = CostModel{ fixed:cs[0], dirty:cs[1], clean:cs[2], eval:cs[3], create:cs[4], r2:0.0 };
+  let mean_y = ys.iter().sum::<f64>() / ys.len() as f64;
+  let (sse, sst) = rows.iter().zip(ys.iter()).fold((0.0, 0.0), |(sse, sst), (row, y)| {
+    let e = y - row.iter().zip(cs.iter()).fold(0.0, |s, (f, c)| s + f * c);
+    (sse + e * e, sst + (y - mean_y) * (y - mean_y))
+  });
+  let model = CostModel{ r2: if sst == 0.0 { 0.0 } else { 1.0 - sse / sst }, .. model };
+  let residuals = metrics.iter().map(|ms| {
+    let (sum, sum_rel) = ms.iter().fold((0.0, 0.0), |(sum, sum_rel), m| {
+      let y = m.time_ns as f64;
+      let e = y - model.predict(m);
+      (sum + e, sum_rel + if y == 0.0 { 0.0 } else { e.abs() / y })
+    });
+    let n = if ms.len() == 0 { 1.0 } else { ms.len() as f64 };
+    CostResidual{ samples: ms.len(), mean: sum / n, mean_rel: sum_rel / n }
+  }).collect();
+  Some((model, residuals))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn linear_fit_exact_line() {
+    let xs = vec![ 1.0, 2.0, 3.0, 4.0 ];
+    let ys = xs.iter().map(|x| 2.0 * x + 1.0).collect();
+    let (slope, intercept, r2) = linear_fit(&xs, &ys);
+    assert!( (slope - 2.0).abs() < 1e-12 );
+    assert!( (intercept - 1.0).abs() < 1e-12 );
+    assert!( (r2 - 1.0).abs() < 1e-12 );
+  }
+
+  #[test]
+  fn linear_fit_constant_xs() {
+    let (slope, intercept, r2) = linear_fit(&vec![ 3.0, 3.0 ], &vec![ 1.0, 2.0 ]);
+    assert_eq!( (slope, intercept, r2), (0.0, 1.5, 0.0) );
+  }
+
+  fn sizes() -> Vec<usize> { vec![ 8, 16, 32, 64, 128, 256 ] }
+
+  #[test]
+  fn fit_complexity_linear() {
+    let points = sizes().into_iter().map(|n| (n, 5.0 * n as f64)).collect();
+    let fit = fit_complexity(&Measure::NaiveTime, points).unwrap();
+    assert_eq!( fit.best().complexity, Complexity::Linear );
+    assert!( (fit.best().coeff - 5.0).abs() < 1e-9 );
+    assert!( (fit.log_slope - 1.0).abs() < 1e-9 );
+  }
+
+  #[test]
+  fn fit_complexity_n_log_n() {
+    let points = sizes().into_iter().map(|n| (n, 3.0 * n as f64 * (n as f64).log2())).collect();
+    let fit = fit_complexity(&Measure::NaiveTime, points).unwrap();
+    assert_eq!( fit.best().complexity, Complexity::NLogN );
+    assert!( (fit.best().r2 - 1.0).abs() < 1e-9 );
+  }
+
+  #[test]
+  fn least_squares_exact() {
+    // y = 3 + 2 a - b
+    let rows = vec![ vec![ 1.0, 0.0, 0.0 ], vec![ 1.0, 1.0, 0.0 ],
+                     vec![ 1.0, 0.0, 1.0 ], vec![ 1.0, 2.0, 3.0 ] ];
+    let ys = rows.iter().map(|r| 3.0 + 2.0 * r[1] - r[2]).collect();
+    let cs = least_squares(&rows, &ys).unwrap();
+    for (c, e) in cs.iter().zip(vec![ 3.0, 2.0, -1.0 ].iter()) { assert!( (c - e).abs() < 1e-9 ) }
+  }
+
+  #[test]
+  fn least_squares_large_counts() {
+    // Counts in the millions: XᵀX entries near 1e12.
+    let rows : Vec<Vec<f64>> = (1..6).map(|i| vec![ 1.0, (i * 1_000_000) as f64, (i * i * 1_000) as f64 ]).collect();
+    let ys = rows.iter().map(|r| 10.0 + 0.5 * r[1] + 4.0 * r[2]).collect();
+    let cs = least_squares(&rows, &ys).unwrap();
+    assert!( (cs[1] - 0.5).abs() < 1e-6 );
+    assert!( (cs[2] - 4.0).abs() < 1e-6 );
+  }
+
+  #[test]
+  fn least_squares_singular() {
+    // The second column is twice the first.
+    let rows = vec![ vec![ 1.0, 2.0 ], vec![ 2.0, 4.0 ], vec![ 3.0, 6.0 ] ];
+    assert!( least_squares(&rows, &vec![ 1.0, 2.0, 3.0 ]).is_none() );
+    assert!( least_squares(&vec![], &vec![]).is_none() );
+  }
+
+  #[test]
+  fn least_squares_nonzero_drops_zero_columns() {
+    let rows = vec![ vec![ 1.0, 0.0, 1.0 ], vec![ 1.0, 0.0, 2.0 ], vec![ 1.0, 0.0, 3.0 ] ];
+    assert!( least_squares(&rows, &vec![ 3.0, 5.0, 7.0 ]).is_none() );
+    let cs = least_squares_nonzero(&rows, &vec![ 3.0, 5.0, 7.0 ]).unwrap();
+    assert!( (cs[0] - 1.0).abs() < 1e-9 );
+    assert_eq!( cs[1], 0.0 );
+    assert!( (cs[2] - 2.0).abs() < 1e-9 );
+  }
+
+  #[test]
+  fn fit_complexity_too_few_or_nonpositive() {
+    assert!( fit_complexity(&Measure::NaiveTime, vec![ (8, 1.0) ]).is_none() );
+    assert!( fit_complexity(&Measure::NaiveTime, vec![ (8, 1.0), (16, 0.0) ]).is_none() );
+  }
+}
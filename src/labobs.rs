@@ -0,0 +1,86 @@
+use adapton::collections::{List, vec_of_list};
+use labdef::SampleParams;
+
+use rand::Rng;
+use std::any::Any;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// _observer_: A named observation of a lab's output, e.g., forcing
+/// its first `k` elements.  After each computation, the runner runs
+/// each observer of the lab in its own shadow of the engine that
+/// computed the output (See `labrun::LabShadowState`), and with its
+/// own Rng (See `labrun::observer_seeds`), so that no observer
+/// perturbs the measured engine, nor the other observers; an
+/// observation depends only on the prior observations of the same
+/// observer.  Each shadow repeats the work of its engine, so each
+/// observer adds about the work of one more engine to each batch.
+/// The runner times each observation, and validates it against that
+/// of the reference engine, separately from the output (See
+/// `labdef::Observation`).
+pub struct Observer<Output> {
+  pub name:    String,
+  /// Observes the output.  The Rng is the same for each engine; it
+  /// chooses, e.g., a random index to observe.
+  pub observe: Box<Fn(&Output, &mut Rng, &SampleParams) -> Box<Any>>,
+  /// Whether two observations (from `observe`) are equivalent.
+  pub equiv:   Box<Fn(&Any, &Any) -> bool>,
+}
+
+fn equiv_of_any<Obs:'static+Eq>(o1:&Any, o2:&Any) -> bool {
+  match (o1.downcast_ref::<Obs>(), o2.downcast_ref::<Obs>()) {
+    (Some(o1), Some(o2)) => o1 == o2,
+    _ => false,
+  }
+}
+
+impl<Output:'static> Observer<Output> {
+  /// An observer whose observations are compared by equality.
+  pub fn new<Obs,F>(name:&str, observe:F) -> Observer<Output>
+    where Obs:'static+Eq,
+          F:'static+Fn(&Output, &mut Rng, &SampleParams) -> Obs
+  {
+    Observer{
+      name:    String::from(name),
+      observe: Box::new(move |output, rng, params| Box::new(observe(output, rng, params)) as Box<Any>),
+      equiv:   Box::new(equiv_of_any::<Obs>),
+    }
+  }
+}
+
+/// Forces the first `k` elements of the list.
+pub fn list_head<X:'static+Clone+Debug+Eq+Hash>(k:usize) -> Observer<List<X>> {
+  Observer::new(&format!("head-{}", k), move |list:&List<X>, _rng:&mut Rng, _params:&SampleParams| {
+    vec_of_list(list.clone(), Some(k))
+  })
+}
+
+/// Forces the last element of the list (and hence, every element).
+pub fn list_last<X:'static+Clone+Debug+Eq+Hash>() -> Observer<List<X>> {
+  Observer::new("last", |list:&List<X>, _rng:&mut Rng, _params:&SampleParams| {
+    vec_of_list(list.clone(), None).pop()
+  })
+}
+
+/// Forces the element at a random index, at most the input size
+/// (See `GenerateParams::size`), or the last element, for shorter
+/// lists.
+pub fn list_random_index<X:'static+Clone+Debug+Eq+Hash>() -> Observer<List<X>> {
+  Observer::new("random-index", |list:&List<X>, rng:&mut Rng, params:&SampleParams| {
+    let i = (rng.next_u64() as usize) % (params.generate_params.size + 1);
+    vec_of_list(list.clone(), Some(i + 1)).pop()
+  })
+}
+
+/// Forces every element of the list, in order.
+pub fn list_traverse<X:'static+Clone+Debug+Eq+Hash>() -> Observer<List<X>> {
+  Observer::new("traverse", |list:&List<X>, _rng:&mut Rng, _params:&SampleParams| {
+    vec_of_list(list.clone(), None)
+  })
+}
+
+/// The standard observers of a list: its head of length `k`, its
+/// last element, an element at a random index, and a full traversal.
+pub fn list_observers<X:'static+Clone+Debug+Eq+Hash>(k:usize) -> Vec<Observer<List<X>>> {
+  vec![ list_head(k), list_last(), list_random_index(), list_traverse() ]
+}
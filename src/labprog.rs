@@ -0,0 +1,301 @@
+use labdef::{Sample, EngineSample, LabError};
+
+use std::io;
+use std::io::prelude::*;
+
+/// The progress of a lab run, after one of its samples (See
+/// `LabProgress`).
+#[derive(Clone,Debug)]
+pub struct Progress {
+  /// The batch of the latest sample (See `Sample::batch_name`).
+  pub batch_name:  usize,
+  /// The number of batches of the full run, i.e.,
+  /// `LabParams::change_batch_loopc + 1`.
+  pub batch_count: usize,
+  /// The time since the run began, including that of the runner.
+  pub elapsed_ns:  u64,
+}
+
+impl Progress {
+  /// The number of batches completed so far.
+  pub fn batches_done(self:&Self) -> usize { self.batch_name + 1 }
+  /// The estimated time until the run ends, assuming that each
+  /// remaining batch takes the mean time of those so far.
+  pub fn eta_ns(self:&Self) -> u64 {
+    let done = self.batches_done() as u64;
+    let left = (self.batch_count as u64).saturating_sub(done);
+    // Multiply before dividing, so as not to truncate the mean time
+    // per batch; in floating point, so as not to overflow.
+    (self.elapsed_ns as f64 * left as f64 / done as f64) as u64
+  }
+}
+
+/// Whether a lab run continues after a sample.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum Flow {
+  Continue,
+  /// Stop the run early, for the given reason; the results record it
+  /// (See `LabResults::stopped`).
+  Stop(String),
+}
+
+/// _lab progress_: A caller's view of a lab run, as it runs (See
+/// `Lab::run_with`).  The runner gives it each sample, as soon as
+/// it is collected, e.g., to stream it to disk, or to print progress;
+/// it may stop the run early, e.g., at an invalid output, or after a
+/// time budget.  The results still hold every sample.
+pub trait LabProgress {
+  /// After each sample; whether to continue.
+  fn on_sample(self:&mut Self, progress:&Progress, sample:&Sample) -> Flow;
+  /// After a failure of the lab's editor, which ends the run.
+  fn on_error(self:&mut Self, _error:&LabError) { }
+}
+
+/// A closure is a lab progress (See `LabProgress::on_sample`).
+impl<F:FnMut(&Progress, &Sample) -> Flow> LabProgress for F {
+  fn on_sample(self:&mut Self, progress:&Progress, sample:&Sample) -> Flow {
+    self(progress, sample)
+  }
+}
+
+/// Ignores the progress; the run continues to its last batch.
+pub struct NoProgress;
+
+impl LabProgress for NoProgress {
+  fn on_sample(self:&mut Self, _progress:&Progress, _sample:&Sample) -> Flow { Flow::Continue }
+}
+
+/// Prints each batch of the run, with its elapsed time and an
+/// estimate of the remaining time; prints any failure of the editor.
+pub struct PrintProgress;
+
+impl LabProgress for PrintProgress {
+  fn on_sample(self:&mut Self, progress:&Progress, _sample:&Sample) -> Flow {
+    println!("  batch {}/{}: {:.*} ms elapsed, ETA {:.*} ms",
+             progress.batches_done(), progress.batch_count,
+             1, progress.elapsed_ns as f64 / 1_000_000.0,
+             1, progress.eta_ns() as f64 / 1_000_000.0);
+    Flow::Continue
+  }
+  fn on_error(self:&mut Self, error:&LabError) {
+    println!("  error in batch {}, {}: {}", error.batch_name, error.engine.name(), error.error.descr());
+  }
+}
+
+/// Stops the run at the first sample with an invalid input, output or
+/// observation (See `Sample::input_valid` and `Sample::output_valid`).
+pub struct StopOnInvalid;
+
+impl LabProgress for StopOnInvalid {
+  fn on_sample(self:&mut Self, _progress:&Progress, sample:&Sample) -> Flow {
+    let invalid_observation = sample.engine_samples.iter().any(|s| {
+      s.observations.iter().any(|o| o.valid == Some(false))
+    });
+    if sample.input_valid == Some(false) {
+      Flow::Stop(format!("invalid input in batch {}", sample.batch_name))
+    } else if sample.output_valid == Some(false) {
+      Flow::Stop(format!("invalid output in batch {}", sample.batch_name))
+    } else if invalid_observation {
+      Flow::Stop(format!("invalid observation in batch {}", sample.batch_name))
+    } else {
+      Flow::Continue
+    }
+  }
+}
+
+/// Stops the run once it exceeds a time budget, in nanoseconds; the
+/// batch underway when the budget runs out still completes.
+pub struct TimeBudget {
+  pub budget_ns: u64,
+}
+
+impl LabProgress for TimeBudget {
+  fn on_sample(self:&mut Self, progress:&Progress, _sample:&Sample) -> Flow {
+    if progress.elapsed_ns > self.budget_ns {
+      Flow::Stop(format!("time budget of {} ms exceeded", self.budget_ns / 1_000_000))
+    } else {
+      Flow::Continue
+    }
+  }
+}
+
+/// Streams each sample to the writer as it is collected, as
+/// tab-separated values, one line per engine, after a header line.
+/// Stops the run at the first failure to write.
+pub struct StreamSamples<W:Write> {
+  pub writer: W,
+  /// The failure to write the header, if any; it stops the run at
+  /// the first sample.
+  error: Option<String>,
+}
+
+impl<W:Write> StreamSamples<W> {
+  pub fn new(writer:W) -> StreamSamples<W> {
+    let mut writer = writer;
+    let error = writeln!(writer, "batch\tdemand_only\tdemand\tengine\tedit_ns\tcompute_ns\tinput_valid\toutput_valid")
+      .err().map(|err| err.to_string());
+    StreamSamples{ writer, error }
+  }
+}
+
+fn string_of_valid(valid:&Option<bool>) -> &'static str {
+  match *valid { None => "", Some(true) => "true", Some(false) => "false" }
+}
+
+fn write_engine_sample<W:Write>(writer:&mut W, sample:&Sample, es:&EngineSample) -> io::Result<()> {
+  writeln!(writer, "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+           sample.batch_name, sample.demand_only, sample.demand, es.engine.name(),
+           es.process_input.time_ns, es.compute_output.time_ns,
+           string_of_valid(&es.input_valid), string_of_valid(&es.output_valid))
+}
+
+impl<W:Write> StreamSamples<W> {
+  fn write_sample(self:&mut Self, sample:&Sample) -> io::Result<()> {
+    for es in sample.engine_samples.iter() {
+      write_engine_sample(&mut self.writer, sample, es)?
+    };
+    self.writer.flush()
+  }
+}
+
+impl<W:Write> LabProgress for StreamSamples<W> {
+  fn on_sample(self:&mut Self, _progress:&Progress, sample:&Sample) -> Flow {
+    if let Some(ref err) = self.error {
+      return Flow::Stop(err.clone())
+    };
+    match self.write_sample(sample) {
+      Ok(()) => Flow::Continue,
+      Err(err) => Flow::Stop(err.to_string()),
+    }
+  }
+}
+
+/// Several lab progresses, in order; the run stops when any of them
+/// stops it (though each still sees each sample).
+pub struct Progresses {
+  pub progresses: Vec<Box<LabProgress>>,
+}
+
+impl LabProgress for Progresses {
+  fn on_sample(self:&mut Self, progress:&Progress, sample:&Sample) -> Flow {
+    let mut flow = Flow::Continue;
+    for p in self.progresses.iter_mut() {
+      match p.on_sample(progress, sample) {
+        Flow::Continue => (),
+        Flow::Stop(reason) => if flow == Flow::Continue { flow = Flow::Stop(reason) },
+      }
+    };
+    flow
+  }
+  fn on_error(self:&mut Self, error:&LabError) {
+    for p in self.progresses.iter_mut() { p.on_error(error) }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use labdef::{EngineConfig, EngineMetrics, Observation};
+  use labstats::EffectCnt;
+  use adapton::engine::cnt;
+
+  fn progress(batch_name:usize, elapsed_ns:u64) -> Progress {
+    Progress{ batch_name, batch_count: 10, elapsed_ns }
+  }
+
+  fn sample(batch_name:usize, input_valid:Option<bool>, output_valid:Option<bool>) -> Sample {
+    Sample{ batch_name, engine_samples: vec![], input_valid, output_valid, demand_only: false, demand: 0 }
+  }
+
+  fn metrics() -> EngineMetrics {
+    let (_, engine_cnt) = cnt(|| ());
+    EngineMetrics{ time_ns: 0, engine_cnt, effect_cnt: EffectCnt::default(),
+                   reflect_traces: vec![], reflect_dcg: None, dcg_diagnostics: vec![] }
+  }
+
+  fn engine_sample(observation_valid:Option<bool>) -> EngineSample {
+    EngineSample{
+      engine: EngineConfig::DCG,
+      process_input: metrics(), compute_output: metrics(),
+      input: None, output: None,
+      input_valid: Some(true), output_valid: Some(true),
+      observations: vec![ Observation{ name: String::from("last"), metrics: metrics(), valid: observation_valid } ],
+      replay_time_ns: None,
+    }
+  }
+
+  #[test]
+  fn eta_ns() {
+    // Two of ten batches in 20 ns: eight more batches, of 10 ns each.
+    assert_eq!( progress(1, 20).eta_ns(), 80 );
+    assert_eq!( progress(0, 7).eta_ns(), 63 );
+    // Three batches in 10 ns: seven more, of 3.33 ns each.
+    assert_eq!( progress(2, 10).eta_ns(), 23 );
+    assert_eq!( progress(9, 100).eta_ns(), 0 );
+    // More batches than expected, e.g., after a change of parameters.
+    assert_eq!( progress(12, 100).eta_ns(), 0 );
+  }
+
+  #[test]
+  fn stop_on_invalid() {
+    let p = progress(3, 0);
+    let mut stop = StopOnInvalid;
+    assert_eq!( stop.on_sample(&p, &sample(3, None, None)), Flow::Continue );
+    assert_eq!( stop.on_sample(&p, &sample(3, Some(true), Some(true))), Flow::Continue );
+    assert_eq!( stop.on_sample(&p, &sample(3, Some(false), Some(false))),
+                Flow::Stop(String::from("invalid input in batch 3")) );
+    assert_eq!( stop.on_sample(&p, &sample(3, Some(true), Some(false))),
+                Flow::Stop(String::from("invalid output in batch 3")) );
+    let mut s = sample(3, Some(true), Some(true));
+    s.engine_samples.push(engine_sample(Some(true)));
+    assert_eq!( stop.on_sample(&p, &s), Flow::Continue );
+    s.engine_samples.push(engine_sample(Some(false)));
+    assert_eq!( stop.on_sample(&p, &s), Flow::Stop(String::from("invalid observation in batch 3")) );
+  }
+
+  /// The first reason to stop wins, but every progress sees every sample.
+  #[test]
+  fn progresses() {
+    use std::rc::Rc;
+    use std::cell::RefCell;
+    let seen : Rc<RefCell<Vec<(usize, usize)>>> = Rc::new(RefCell::new(vec![]));
+    let counter = |i:usize, stop_at:usize| {
+      let seen = seen.clone();
+      Box::new(move |_p:&Progress, s:&Sample| {
+        seen.borrow_mut().push((i, s.batch_name));
+        if s.batch_name >= stop_at { Flow::Stop(format!("{} stops", i)) } else { Flow::Continue }
+      }) as Box<LabProgress>
+    };
+    let mut ps = Progresses{ progresses: vec![ counter(0, 2), counter(1, 1), counter(2, 5) ] };
+    assert_eq!( ps.on_sample(&progress(0, 0), &sample(0, None, None)), Flow::Continue );
+    assert_eq!( ps.on_sample(&progress(1, 0), &sample(1, None, None)), Flow::Stop(String::from("1 stops")) );
+    assert_eq!( ps.on_sample(&progress(2, 0), &sample(2, None, None)), Flow::Stop(String::from("0 stops")) );
+    assert_eq!( *seen.borrow(), vec![ (0, 0), (1, 0), (2, 0),
+                                      (0, 1), (1, 1), (2, 1),
+                                      (0, 2), (1, 2), (2, 2) ] );
+  }
+
+  struct FailingWriter;
+
+  impl Write for FailingWriter {
+    fn write(&mut self, _buf:&[u8]) -> io::Result<usize> {
+      Err(io::Error::new(io::ErrorKind::Other, "disk full"))
+    }
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+  }
+
+  /// A failure to write stops the run, rather than panicking.
+  #[test]
+  fn stream_samples_stops_on_error() {
+    let mut stream = StreamSamples::new(FailingWriter);
+    assert_eq!( stream.on_sample(&progress(0, 0), &sample(0, None, None)),
+                Flow::Stop(String::from("disk full")) );
+    let mut stream = StreamSamples::new(Vec::<u8>::new());
+    let mut s = sample(0, Some(true), Some(true));
+    s.engine_samples.push(engine_sample(Some(true)));
+    assert_eq!( stream.on_sample(&progress(0, 0), &s), Flow::Continue );
+    let text = String::from_utf8(stream.writer).unwrap();
+    assert_eq!( text.lines().count(), 2 );
+    assert_eq!( text.lines().nth(1), Some("0\tfalse\t0\tDCG\t0\t0\ttrue\ttrue") );
+  }
+}
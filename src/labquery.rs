@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+
+use labdef::{Sample, EngineSample, EngineConfig};
+use labstats::locs_of_val;
+use adapton::engine::reflect::{DCG, Loc, Node, Val, Effect, trace, succs_of_node, string_of_loc};
+
+/// For each location that some node of the DCG allocates, the
+/// location of an allocating node.  Locations that no node allocates
+/// (e.g., those that the editor allocates) have no entry.
+pub fn allocators(dcg:&DCG) -> HashMap<Loc, Loc> {
+  let mut allocs = HashMap::new();
+  for (loc, node) in dcg.table.iter() {
+    if let Some(succs) = succs_of_node(node) {
+      for succ in succs.iter() {
+        if succ.effect == Effect::Alloc {
+          allocs.insert(succ.loc.clone(), loc.clone());
+        }
+      }
+    }
+  };
+  allocs
+}
+
+/// The value of a node of the DCG, if any.
+pub fn value_of_node(node:&Node) -> Option<&Val> {
+  match *node {
+    Node::Pure(ref n) => Some(&n.value),
+    Node::Ref(ref n)  => Some(&n.value),
+    Node::Comp(ref n) => n.value.as_ref(),
+  }
+}
+
+/// The locations of the articulations within the given value,
+/// transitively, via the values of the DCG's nodes (but not via
+/// their successor edges).  E.g., for a reflected input, these are
+/// the locations of all of its cells.
+pub fn locs_of_val_closure(dcg:&DCG, val:&Val) -> HashMap<Loc, ()> {
+  let mut reached = HashMap::new();
+  let mut stack = vec![];
+  locs_of_val(val, &mut stack);
+  while let Some(loc) = stack.pop() {
+    if reached.contains_key(&loc) { continue };
+    if let Some(v) = dcg.table.get(&loc).and_then(value_of_node) {
+      locs_of_val(v, &mut stack)
+    };
+    reached.insert(loc, ());
+  };
+  reached
+}
+
+/// Backward slice: The locations that the given location
+/// transitively depends on.  A computation depends on the locations
+/// that it forces; a location that a computation allocates depends
+/// on that computation.  Includes the given location.
+pub fn backward_slice(dcg:&DCG, allocs:&HashMap<Loc, Loc>, loc:&Loc) -> HashMap<Loc, ()> {
+  let mut reached = HashMap::new();
+  let mut stack = vec![ loc.clone() ];
+  while let Some(loc) = stack.pop() {
+    if reached.contains_key(&loc) { continue };
+    if let Some(succs) = dcg.table.get(&loc).and_then(succs_of_node) {
+      for succ in succs.iter() {
+        if succ.effect == Effect::Force { stack.push(succ.loc.clone()) }
+      }
+    };
+    if let Some(alloc) = allocs.get(&loc) { stack.push(alloc.clone()) };
+    reached.insert(loc, ());
+  };
+  reached
+}
+
+/// Provenance query: The input locations that the given (output)
+/// location transitively reads, in order of their strings.  The
+/// input locations are those within the reflected input value (See
+/// `locs_of_val_closure`).
+pub fn provenance(dcg:&DCG, input:&Val, loc:&Loc) -> Vec<Loc> {
+  let input_locs = locs_of_val_closure(dcg, input);
+  provenance_of_input_locs(dcg, &allocators(dcg), &input_locs, loc)
+}
+
+fn provenance_of_input_locs(dcg:&DCG, allocs:&HashMap<Loc, Loc>,
+                            input_locs:&HashMap<Loc, ()>, loc:&Loc) -> Vec<Loc> {
+  let mut locs : Vec<Loc> = backward_slice(dcg, allocs, loc).into_iter()
+    .map(|(l, _)| l)
+    .filter(|l| input_locs.contains_key(l))
+    .collect();
+  locs.sort_by_key(|l| string_of_loc(l));
+  locs
+}
+
+/// The provenance of every output location: For each location within
+/// the reflected output value (transitively), its provenance in the
+/// reflected input value (See `provenance`).
+pub fn output_provenance(dcg:&DCG, input:&Val, output:&Val) -> Vec<(Loc, Vec<Loc>)> {
+  let allocs     = allocators(dcg);
+  let input_locs = locs_of_val_closure(dcg, input);
+  let mut output_locs : Vec<Loc> = locs_of_val_closure(dcg, output).into_iter().map(|(l, _)| l).collect();
+  output_locs.sort_by_key(|l| string_of_loc(l));
+  output_locs.into_iter().map(|loc| {
+    let prov = provenance_of_input_locs(dcg, &allocs, &input_locs, &loc);
+    (loc, prov)
+  }).collect()
+}
+
+/// The locations that the given traces allocate (fresh, or existing),
+/// at any depth.  For the editor's traces, these are the input
+/// locations that the edit changed.
+pub fn alloc_locs_of_traces(traces:&Vec<trace::Trace>, locs:&mut Vec<Loc>) {
+  for tr in traces.iter() {
+    match tr.effect {
+      trace::Effect::Alloc(_, _) => locs.push(tr.edge.succ.loc.clone()),
+      _ => (),
+    };
+    alloc_locs_of_traces(&tr.extent, locs)
+  }
+}
+
+/// The locations that the given traces (re-)evaluate, at any depth:
+/// cache misses (`Force(CompCacheMiss)`) and cleanings (`CleanEval`).
+pub fn eval_locs_of_traces(traces:&Vec<trace::Trace>, locs:&mut HashMap<Loc, ()>) {
+  for tr in traces.iter() {
+    match tr.effect {
+      trace::Effect::Force(trace::ForceCase::CompCacheMiss) |
+      trace::Effect::CleanEval => { locs.insert(tr.edge.succ.loc.clone(), ()); },
+      _ => (),
+    };
+    eval_locs_of_traces(&tr.extent, locs)
+  }
+}
+
+/// Forward slice: The locations that transitively depend on the given
+/// (changed) locations, via the inverse of force edges: a computation
+/// depends on the locations that it forces.  (We do not follow
+/// allocation edges: a computation that re-evaluates may, or may not,
+/// re-allocate the same locations; if it does, their forcers depend
+/// on the computation through what they force.)  Includes the given
+/// locations.
+pub fn forward_slice(dcg:&DCG, changed:&Vec<Loc>) -> HashMap<Loc, ()> {
+  // Invert the force edges: for each location, its forcing computations.
+  let mut forcers : HashMap<Loc, Vec<Loc>> = HashMap::new();
+  for (loc, node) in dcg.table.iter() {
+    if let Some(succs) = succs_of_node(node) {
+      for succ in succs.iter() {
+        if succ.effect == Effect::Force {
+          forcers.entry(succ.loc.clone()).or_insert(vec![]).push(loc.clone())
+        }
+      }
+    }
+  };
+  let mut reached = HashMap::new();
+  let mut stack = changed.clone();
+  while let Some(loc) = stack.pop() {
+    if reached.contains_key(&loc) { continue };
+    if let Some(fs) = forcers.get(&loc) {
+      for f in fs.iter() { stack.push(f.clone()) }
+    };
+    reached.insert(loc, ());
+  };
+  reached
+}
+
+/// The impact of the edit of one batch: the forward slice of the
+/// input locations that it changed, versus the computations that the
+/// update actually re-evaluated.
+#[derive(Clone,Debug)]
+pub struct Impact {
+  pub batch_name:       usize,
+  /// The input locations that the editor changed.
+  pub changed:          Vec<Loc>,
+  /// The size of the forward slice of the changed locations.
+  pub slice_nodes:      usize,
+  /// The computations within the forward slice.
+  pub slice_comps:      usize,
+  /// The (prior) output locations within the forward slice.
+  pub affected_outputs: usize,
+  /// The computations that the update re-evaluated.
+  pub re_evaluated:     usize,
+  /// The re-evaluated computations that are new to the DCG (e.g.,
+  /// over fresh input cells); they are outside of any slice of the
+  /// DCG before the update, but their work is necessary.
+  pub fresh:            usize,
+  /// The re-evaluated computations of the DCG outside the forward
+  /// slice; this work is unnecessary.
+  pub unnecessary:      usize,
+}
+
+/// Impact query: For the batch of `this` sample, the forward slice of
+/// its edit within the DCG after that edit, and before the update
+/// (See `Impact`).  The slice begins at the input locations that the
+/// editor allocated.  The affected outputs are those of the `prev`
+/// sample, whose computation the update reuses.  None unless both
+/// samples reflect their DCGs, and `this` reflects its traces.
+pub fn impact_of_sample(batch_name:usize, prev:&EngineSample, this:&EngineSample) -> Option<Impact> {
+  let dcg = match this.process_input.reflect_dcg { None => return None, Some(ref dcg) => dcg };
+  let prev_output = match prev.output { None => return None, Some(ref v) => v };
+  if this.process_input.reflect_traces.len() == 0 { return None };
+  let mut changed = vec![];
+  alloc_locs_of_traces(&this.process_input.reflect_traces, &mut changed);
+  changed.sort_by_key(|l| string_of_loc(l));
+  changed.dedup();
+  let slice = forward_slice(dcg, &changed);
+  let slice_comps = slice.keys().filter(|l| {
+    match dcg.table.get(*l) { Some(&Node::Comp(_)) => true, _ => false }
+  }).count();
+  let affected_outputs = locs_of_val_closure(dcg, prev_output).keys()
+    .filter(|l| slice.contains_key(*l)).count();
+  let mut evals = HashMap::new();
+  eval_locs_of_traces(&this.compute_output.reflect_traces, &mut evals);
+  let fresh = evals.keys().filter(|l| !dcg.table.contains_key(*l)).count();
+  let unnecessary = evals.keys().filter(|l| dcg.table.contains_key(*l) && !slice.contains_key(*l)).count();
+  Some(Impact{
+    batch_name,
+    slice_nodes: slice.len(),
+    slice_comps,
+    affected_outputs,
+    re_evaluated: evals.len(),
+    fresh,
+    unnecessary,
+    changed,
+  })
+}
+
+/// The impact of each batch of the given engine, but the first, and
+/// those that change only the demand (See `impact_of_sample`, and
+/// `Sample::demand_only`); these edit nothing.
+pub fn impacts(samples:&Vec<Sample>, engine:&EngineConfig) -> Vec<Impact> {
+  samples.iter().zip(samples.iter().skip(1)).filter(|&(_, this)| !this.demand_only).filter_map(|(prev, this)| {
+    match (prev.engine_sample(engine), this.engine_sample(engine)) {
+      (Some(p), Some(t)) => impact_of_sample(this.batch_name, p, t),
+      _ => None,
+    }
+  }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lab_params_defaults;
+  use labreg::LabRegistry;
+  use adapton::engine::name_of_str;
+
+  /// Each edit of `list-eager-map` prepends one fresh cell, which
+  /// nothing in the DCG forces yet: the slice is the cell alone; the
+  /// update re-evaluates only fresh computations, over that cell, and
+  /// reuses every computation of the DCG.
+  #[test]
+  fn impacts_of_prepend() {
+    let registry = LabRegistry::catalog();
+    let lab = registry.labs().iter().find(|l| l.name() == name_of_str("list-eager-map")).unwrap();
+    let mut params = lab_params_defaults();
+    params.change_batch_loopc = 3;
+    let results = lab.run(&params);
+    let impacts = impacts(&results.samples, &EngineConfig::DCG);
+    assert_eq!( impacts.len(), 3 );
+    for impact in impacts.iter() {
+      assert_eq!( impact.changed.len(), 1, "{:?}", impact );
+      assert_eq!( impact.slice_nodes, 1, "{:?}", impact );
+      assert_eq!( impact.slice_comps, 0, "{:?}", impact );
+      assert_eq!( impact.affected_outputs, 0, "{:?}", impact );
+      assert_eq!( impact.unnecessary, 0, "{:?}", impact );
+      assert_eq!( impact.fresh, impact.re_evaluated, "{:?}", impact );
+      assert!( impact.re_evaluated > 0, "{:?}", impact );
+    }
+  }
+}
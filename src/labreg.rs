@@ -0,0 +1,81 @@
+use labdef::{Lab, LabTag};
+use catalog;
+
+use adapton::engine::reflect::string_of_name;
+
+/// A registry of labs: the labs that `run_all_tests` (and the other
+/// entry points of this crate) run, in order of registration.  Other
+/// crates can run their own labs through the harness by registering
+/// them here, with or without the labs of our catalog (See
+/// `catalog::all_labs`).
+pub struct LabRegistry {
+  labs: Vec<Box<Lab>>,
+}
+
+impl LabRegistry {
+  /// An empty registry.
+  pub fn new() -> Self {
+    LabRegistry{ labs:vec![] }
+  }
+
+  /// A registry of all labs in our catalog.
+  pub fn catalog() -> Self {
+    let mut reg = LabRegistry::new();
+    reg.register_all(catalog::all_labs());
+    reg
+  }
+
+  /// Registers a lab, after those already registered.  Lab names
+  /// determine the names of generated output files, so they must be
+  /// unique; panics if the registry already has a lab of this name.
+  pub fn register(self:&mut Self, lab:Box<Lab>) -> &mut Self {
+    let name = string_of_name(&lab.name());
+    if self.labs.iter().any(|l| string_of_name(&l.name()) == name) {
+      panic!("LabRegistry: lab {} is already registered", name)
+    };
+    self.labs.push(lab);
+    self
+  }
+
+  /// Registers each of the given labs, in order (See `register`).
+  pub fn register_all(self:&mut Self, labs:Vec<Box<Lab>>) -> &mut Self {
+    for lab in labs.into_iter() {
+      self.register(lab);
+    };
+    self
+  }
+
+  /// The registered lab with the given name, if any.
+  pub fn lab(self:&Self, name:&str) -> Option<&Box<Lab>> {
+    self.labs.iter().find(|l| string_of_name(&l.name()) == name)
+  }
+
+  /// Keeps only the labs with the given tag (See `LabMeta::tags`).
+  pub fn retain_tagged(self:&mut Self, tag:&LabTag) -> &mut Self {
+    self.labs.retain(|l| l.meta().has_tag(tag));
+    self
+  }
+
+  /// The registered labs, in order of registration.
+  pub fn labs(self:&Self) -> &Vec<Box<Lab>> {
+    &self.labs
+  }
+
+  /// The tags of the registered labs, including named tags (See
+  /// `LabTag::Named`), each once, in order of first use.
+  pub fn tags(self:&Self) -> Vec<LabTag> {
+    let mut tags : Vec<LabTag> = vec![];
+    for lab in self.labs.iter() {
+      for tag in lab.meta().tags.iter() {
+        if !tags.contains(tag) { tags.push(tag.clone()) }
+      }
+    };
+    tags
+  }
+
+  /// The tag with the given name: a standard tag (See `LabTag::all`),
+  /// or else a named tag of some registered lab.
+  pub fn tag_of_name(self:&Self, name:&str) -> Option<LabTag> {
+    LabTag::all().into_iter().chain(self.tags().into_iter()).find(|t| t.name() == name)
+  }
+}
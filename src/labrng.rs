@@ -0,0 +1,175 @@
+use rand::Rng;
+
+/// A random number generator that the runner can seed from
+/// `SampleParams::input_seeds` (See `labdef::LabRng`).  Each
+/// implements a specified algorithm, within this crate, so that its
+/// raw words (`next_u32` and `next_u64`) do not depend on the version
+/// of `rand`; values derived from them through `rand::Rng` (e.g.,
+/// `gen_range`) do.
+pub trait LabRngGen : Rng+Clone {
+  fn from_seeds(seeds:&Vec<usize>) -> Self;
+}
+
+/// The output function (a bijection) of SplitMix64, applied to the
+/// state after its increment.
+fn splitmix64(z:u64) -> u64 {
+  let z = z.wrapping_add(0x9e3779b97f4a7c15);
+  let z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+  let z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+  z ^ (z >> 31)
+}
+
+/// Hashes the seeds (and their number) into `n` words, with
+/// SplitMix64.  Unlike padding or xor-ing the seeds into a state,
+/// distinct seed vectors give distinct words (but for collisions of
+/// the hash), e.g., `[x]`, `[0, 0, x]` and `[y, x, y]`.
+pub fn seed_words(seeds:&Vec<usize>, n:usize) -> Vec<u64> {
+  let mut h = splitmix64(seeds.len() as u64);
+  for s in seeds.iter() {
+    h = splitmix64(h ^ (*s as u64));
+  };
+  let mut words = vec![];
+  for _ in 0..n {
+    h = splitmix64(h);
+    words.push(h);
+  };
+  words
+}
+
+/// PCG32 (PCG-XSH-RR, with 64 bits of state and 32 bits of output),
+/// as in the reference implementation of O'Neill (`pcg32_random_r`,
+/// at http://www.pcg-random.org).
+#[derive(Clone,Debug)]
+pub struct Pcg32 {
+  state: u64,
+  inc:   u64,
+}
+
+impl Pcg32 {
+  /// The generator of the given initial state and sequence, as
+  /// `pcg32_srandom_r`.
+  pub fn new(init_state:u64, init_seq:u64) -> Pcg32 {
+    let mut rng = Pcg32{ state: 0, inc: (init_seq << 1) | 1 };
+    rng.next_u32();
+    rng.state = rng.state.wrapping_add(init_state);
+    rng.next_u32();
+    rng
+  }
+}
+
+impl Rng for Pcg32 {
+  fn next_u32(&mut self) -> u32 {
+    let old = self.state;
+    self.state = old.wrapping_mul(6364136223846793005).wrapping_add(self.inc);
+    let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+    let rot = (old >> 59) as u32;
+    xorshifted.rotate_right(rot)
+  }
+  /// The first output in the high bits, the second in the low bits.
+  fn next_u64(&mut self) -> u64 {
+    let hi = self.next_u32() as u64;
+    let lo = self.next_u32() as u64;
+    (hi << 32) | lo
+  }
+}
+
+impl LabRngGen for Pcg32 {
+  fn from_seeds(seeds:&Vec<usize>) -> Self {
+    let words = seed_words(seeds, 2);
+    Pcg32::new(words[0], words[1])
+  }
+}
+
+/// Xorshift64* (Vigna, "An experimental exploration of Marsaglia's
+/// xorshift generators, scrambled"); fast, but of lower quality than
+/// PCG32.
+#[derive(Clone,Debug)]
+pub struct XorShift64Star {
+  state: u64,
+}
+
+impl XorShift64Star {
+  /// The generator of the given state; Xorshift requires a state
+  /// that is not zero, so zero gives a fixed, non-zero state.
+  pub fn new(state:u64) -> XorShift64Star {
+    XorShift64Star{ state: if state == 0 { 0x9e3779b97f4a7c15 } else { state } }
+  }
+}
+
+impl Rng for XorShift64Star {
+  /// The high bits of the next 64-bit output.
+  fn next_u32(&mut self) -> u32 {
+    (self.next_u64() >> 32) as u32
+  }
+  fn next_u64(&mut self) -> u64 {
+    let mut x = self.state;
+    x ^= x >> 12;
+    x ^= x << 25;
+    x ^= x >> 27;
+    self.state = x;
+    x.wrapping_mul(0x2545f4914f6cdd1d)
+  }
+}
+
+impl LabRngGen for XorShift64Star {
+  fn from_seeds(seeds:&Vec<usize>) -> Self {
+    XorShift64Star::new(seed_words(seeds, 1)[0])
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn first_u64s<R:LabRngGen>(seeds:&Vec<usize>) -> Vec<u64> {
+    let mut rng = R::from_seeds(seeds);
+    vec![ rng.next_u64(), rng.next_u64(), rng.next_u64() ]
+  }
+
+  /// The outputs of the reference implementation, for its demo seeds.
+  #[test]
+  fn pcg32_reference() {
+    let mut rng = Pcg32::new(42, 54);
+    let xs : Vec<u32> = (0..6).map(|_| rng.next_u32()).collect();
+    assert_eq!( xs, vec![ 0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e ] );
+  }
+
+  #[test]
+  fn pcg32_from_seeds() {
+    assert_eq!( first_u64s::<Pcg32>(&vec![]),
+                vec![ 0xa9b46ecbabf26e2c, 0xc12878198182fa94, 0xaab7da2d7f7e6bcf ] );
+    assert_eq!( first_u64s::<Pcg32>(&vec![0]),
+                vec![ 0xc67338c1998010d3, 0x40d623c5af816fb3, 0x2ee82a91f150c13b ] );
+    assert_eq!( first_u64s::<Pcg32>(&vec![0, 0]),
+                vec![ 0x7a1059dbac6691af, 0x71ab4228292279b4, 0xc2af00ead5af0af6 ] );
+    assert_eq!( first_u64s::<Pcg32>(&vec![1, 2, 3]),
+                vec![ 0x400916d885f05c0d, 0x2c0f83dff07007a3, 0x0583f23ac053e57f ] );
+  }
+
+  #[test]
+  fn xorshift64star_reference() {
+    let mut rng = XorShift64Star::new(1);
+    let xs : Vec<u64> = (0..3).map(|_| rng.next_u64()).collect();
+    assert_eq!( xs, vec![ 0x47e4ce4b896cdd1d, 0xabcfa6a8e079651d, 0xb9d10d8feb731f57 ] );
+  }
+
+  #[test]
+  fn xorshift64star_from_seeds() {
+    assert_eq!( first_u64s::<XorShift64Star>(&vec![]),
+                vec![ 0x25cf8bb51744a6a1, 0x15fdd1fb8bd5ba2a, 0x91b47737d28902be ] );
+    assert_eq!( first_u64s::<XorShift64Star>(&vec![0]),
+                vec![ 0xbc7c39ddb08d3a61, 0x43fa439ce963e654, 0x6259b85780b14023 ] );
+    assert_eq!( first_u64s::<XorShift64Star>(&vec![0, 0]),
+                vec![ 0x9843b328420c76d0, 0x56d0ba9cf73cc38b, 0x77bae43927fc403a ] );
+    assert_eq!( first_u64s::<XorShift64Star>(&vec![1, 2, 3]),
+                vec![ 0x7db80f4804cf617f, 0x9c450ce5961bcb0f, 0x54e5c12297f7044e ] );
+  }
+
+  /// Seeds that a padded or xor-ed state would confuse.
+  #[test]
+  fn seed_words_distinct() {
+    assert!( seed_words(&vec![5], 2) != seed_words(&vec![0, 0, 5], 2) );
+    assert!( seed_words(&vec![5], 2) != seed_words(&vec![5, 0], 2) );
+    assert!( seed_words(&vec![9], 2) != seed_words(&vec![7, 9, 7], 2) );
+  }
+}
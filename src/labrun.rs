@@ -3,36 +3,74 @@ extern crate rand;
 
 use std::fmt::Debug;
 use labdef::*;
+use labcheck::{check_dcg, force_roots_of_traces};
+use labstats::effect_cnt_of_traces;
+use labprog::{LabProgress, Progress, Flow};
 use std::marker::PhantomData;
 
 use adapton::engine::*;
-use rand::{Rng, SeedableRng};
-use std::mem::swap;
+use rand::Rng;
+use labrng::{LabRngGen, Pcg32, XorShift64Star};
+use std::mem::replace;
+use std::any::Any;
 
 pub trait SampleGen {
   fn sample(self:&mut Self) -> Option<Sample>;
 }
 
-pub struct LabEngineState<Input,EditSt,Output,
-                           Editor:Generate<Input>+Edit<Input,EditSt>,
-                           Archivist:ComputeDemand<Input,Output>> {
+pub struct LabEngineState<Input,EditSt,Output> {
+  pub config:   EngineConfig,
   pub engine:   Engine,
   pub input:    Option<(Input,EditSt)>,
-  inputdist:    PhantomData<Editor>,
-  computer:     PhantomData<Archivist>,
+  /// The shadow in which we take the view of the output, to validate
+  /// it (See `LabShadowState`), when the view may force the output
+  /// (See `OutputEquiv::view_forces`); None for the naive engine, for
+  /// views that force nothing, or when not validating output.
+  pub validate_shadow: Option<LabShadowState<Input,EditSt>>,
+  /// One shadow per observer of the lab, in which it observes the
+  /// output (See `labobs`); empty for the naive engine.  Each shadow
+  /// repeats the edits and the computation of its engine (and, from
+  /// scratch, replays every prior edit), so each observer adds about
+  /// the work of one more engine to each batch.
+  pub observer_shadows: Vec<LabShadowState<Input,EditSt>>,
   output:       PhantomData<Output>,
 }
 
-pub struct LabState<R:Rng+Clone,
-                     Input,EditSt,Output,
-                     Editor:Generate<Input>+Edit<Input,EditSt>,
-                     Archivist:ComputeDemand<Input,Output>> {
+/// A shadow of an engine: a separate engine that follows the same
+/// sequence of edits and computations, but whose metrics we do not
+/// report.  We force the shadow's output (e.g., to validate or to
+/// observe it), rather than that of the measured engine, so that the
+/// forcing does not leave nodes in the measured DCG, where they would
+/// skew the metrics of later batches (and defeat the demand of lazy
+/// computations).
+pub struct LabShadowState<Input,EditSt> {
+  pub engine: Engine,
+  pub input:  Option<(Input,EditSt)>,
+}
+
+pub struct LabState<'a,R:LabRngGen,Input:'a,EditSt:'a,Output:'a> {
+  /// The editor and archivist of the lab.
+  pub fns:              &'a LabFns<Input,EditSt,Output>,
   pub params:           LabParams,
+  /// The Rng before generating the input; for replaying edits from scratch.
+  pub init_rng:         Box<R>,
   pub rng:              Box<R>,
+  /// One Rng per observer (See `labobs`), in order; the same for
+  /// each engine.
+  pub obs_rngs:         Vec<Box<R>>,
+  /// The Rng for the demand (See `LabParams::demand_change`).
+  pub demand_rng:       Box<R>,
   pub change_batch_num: usize,
-  pub dcg_state:   LabEngineState<Input,EditSt,Output,Editor,Archivist>,
-  pub naive_state: LabEngineState<Input,EditSt,Output,Editor,Archivist>,
-  pub samples:     Vec<Sample>,
+  /// The number of batches so far that edited the input; from
+  /// scratch, we replay this many edits.
+  pub edit_batch_num:   usize,
+  /// The current demand (See `LabParams::demand_change`).
+  pub demand:           usize,
+  /// One state per engine configuration, in the order of `LabParams::engines`.
+  pub engine_states: Vec<LabEngineState<Input,EditSt,Output>>,
+  pub samples:       Vec<Sample>,
+  /// The editor's failure, if any; sampling stops at the first.
+  pub errors:        Vec<LabError>,
 }
 
       
@@ -44,35 +82,69 @@ fn get_engine_metrics<X,F:FnOnce() -> X> (params:&SampleParams, thunk:F) -> (X,E
   let time_end = time::precise_time_ns();
   let traces = if params.reflect_trace { reflect::dcg_reflect_end() } else { vec![ ] };
   let dcg    = if params.reflect_dcg   { reflect::dcg_reflect_now() } else { None };
+  let effect_cnt = effect_cnt_of_traces(&traces);
   return (x, EngineMetrics{
     time_ns:time_end - time_start,
     engine_cnt:cnt,
+    effect_cnt,
     reflect_traces:traces,
     reflect_dcg:dcg,
+    dcg_diagnostics:vec![],
   })
 }
 
 fn get_engine_sample
-  <R:Rng+Clone,
-   Input:Clone+Debug,
-   EditSt,Output:Debug,   
-   Editor:Generate<Input>+Edit<Input,EditSt>,
-   Archivist:ComputeDemand<Input,Output>
+  <Input:Clone+Debug,
+   EditSt,Output:Debug
    > 
-  (rng:&mut R, params:&SampleParams, input:Option<(Input,EditSt)>) -> (Output,Input,EditSt,EngineSample) 
+  (fns:&LabFns<Input,EditSt,Output>, rng:&mut Rng, params:&SampleParams, config:&EngineConfig,
+   input:Option<(Input,EditSt)>, replay:usize, edit:bool) -> Result<(Output,Input,EditSt,EngineSample), EditError>
 {
-  let mut rng2 = rng;
+  let rng2 = rng;
+
+  // From scratch, we regenerate the input and replay the edits of
+  // prior change batches, but for that of this batch (if any); we
+  // time this replay separately, so that the editor's time of each
+  // engine is that of (at most) one batch of edits.
+  let (input, replay_time_ns) = match input {
+    None if replay > 0 || !edit => {
+      let replayc = if edit { replay - 1 } else { replay };
+      let quiet = SampleParams{ reflect_trace:false, reflect_dcg:false, .. params.clone() };
+      let (replayed, replay_metrics) =
+        get_engine_metrics( &quiet,
+          || -> Result<(Input,EditSt),EditError> {
+            let mut input  = fns.generate(&mut *rng2, &params.generate_params)?;
+            let mut editst = fns.edit_init(&mut *rng2, &params.generate_params);
+            for _ in 0..replayc {
+              let (input2, editst2) = fns.edit(input, editst, &mut *rng2, &params.generate_params)?;
+              input = input2; editst = editst2;
+            };
+            Ok((input, editst))
+          });
+      (Some(replayed?), Some(replay_metrics.time_ns))
+    },
+    input => (input, None),
+  };
   
-  let ((edited_input, editst), process_input) : ((Input,EditSt),EngineMetrics) = 
+  let (processed, process_input) : (Result<(Input,EditSt),EditError>,EngineMetrics) = 
     match input {
       None => 
         get_engine_metrics( params,
-          move || ( Editor::generate(&mut rng2, &params.generate_params), 
-                    Editor::edit_init(&mut rng2, &params.generate_params ))),
+          move || -> Result<(Input,EditSt),EditError> { 
+            let input  = fns.generate(rng2, &params.generate_params)?;
+            let editst = fns.edit_init(rng2, &params.generate_params);
+            Ok((input, editst))
+          }),
       Some((input, editst)) => 
-        get_engine_metrics( params,
-          move || Editor::edit(input, editst, &mut rng2, &params.generate_params))
+        if edit {
+          get_engine_metrics( params,
+            move || fns.edit(input, editst, rng2, &params.generate_params))
+        } else {
+          // The demand changes, but not the input.
+          get_engine_metrics( params, move || Ok((input, editst)) )
+        }
     };
+  let (edited_input, editst) = processed?;
 
   let input2  = edited_input.clone();
   
@@ -86,108 +158,388 @@ fn get_engine_sample
          move || 
          get_engine_metrics( 
            params, move || 
-             Archivist::compute(input2, params.demand) 
+             fns.compute(input2, params) 
          ));
 
   let outputr = 
     if params.reflect_dcg { 
       Some(reflect::reflect_val(&output)) 
     } else { None };
+
+  // Check the reflected DCGs; after the editor, the DCG may be dirty.
+  let mut process_input = process_input;
+  let mut compute_output = compute_output;
+  process_input.dcg_diagnostics = match process_input.reflect_dcg {
+    None => vec![],
+    Some(ref dcg) => check_dcg(dcg, &vec![], false),
+  };
+  compute_output.dcg_diagnostics = match compute_output.reflect_dcg {
+    None => vec![],
+    Some(ref dcg) => check_dcg(dcg, &force_roots_of_traces(&compute_output.reflect_traces), true),
+  };
   
   let engine_sample = EngineSample{
+    engine: config.clone(),
     process_input,
     input: input2r,
     compute_output,
     output: outputr,
+    input_valid: None,
+    output_valid: None,
+    observations: vec![],
+    replay_time_ns,
   };
 
-  return (output, edited_input, editst, engine_sample)
+  return Ok((output, edited_input, editst, engine_sample))
 }
 
-fn get_sample_gen
+/// Creates a fresh, empty DCG engine, leaving the naive engine in use.
+fn empty_dcg() -> Engine {
+  // Create empty DCG; TODO-Minor-- Make the API for this better.
+  let _ = init_dcg(); assert!(engine_is_dcg());
+  use_engine(Engine::Naive) // TODO-Minor: Rename this operation: "engine_swap" or something 
+}
+
+/// The initial engine of an engine configuration (or of its shadow).
+fn engine_of_config(config:&EngineConfig) -> Engine {
+  match *config {
+    EngineConfig::Naive => Engine::Naive, // A constant
+    EngineConfig::DCG | 
+    EngineConfig::DCGNoReflect => empty_dcg(),
+    // Replaced by an empty DCG for each sample:
+    EngineConfig::DCGFromScratch => Engine::Naive,
+  }
+}
+
+/// Advances the shadow (See `LabShadowState`) by one sample, just as
+/// the engine that it shadows, from the same Rng, and gives its
+/// output to `thunk`, within the shadow's engine.  Leaves the naive
+/// engine in use.
+fn with_shadow
   <Input:Clone+Debug,
+   EditSt,Output:Debug,
+   X,F:FnOnce(&Output) -> X
+   >
+  (fns:&LabFns<Input,EditSt,Output>, shadow:&mut LabShadowState<Input,EditSt>, config:&EngineConfig,
+   rng:&mut Rng, params:&SampleParams, replay:usize, edit:bool, thunk:F) -> Result<X, EditError>
+{
+  let quiet = SampleParams{ reflect_trace:false, reflect_dcg:false, .. params.clone() };
+  let from_scratch = *config == EngineConfig::DCGFromScratch;
+  let engine = if from_scratch { empty_dcg() } else { replace(&mut shadow.engine, Engine::Naive) };
+  let _ = use_engine(engine);
+  let input = if from_scratch { None } else { shadow.input.take() };
+  let result = get_engine_sample::<Input,EditSt,Output>
+    (fns, rng, &quiet, config, input, replay, edit).map(|(output, input, editst, _)| {
+      let x = thunk(&output);
+      if !from_scratch { shadow.input = Some((input, editst)) };
+      x
+    });
+  let engine = use_engine(Engine::Naive); // Swap out the shadow's engine
+  if !from_scratch { shadow.engine = engine };
+  result
+}
+
+/// Tags the seeds of the observers' Rngs (See `observer_seeds`).
+const OBSERVER_SEED_TAG : usize = 0x6f627365; // "obse"
+/// Tags the seeds of the demand's Rng (See `demand_seeds`).
+const DEMAND_SEED_TAG : usize = 0x64656d61; // "dema"
+
+/// The seeds of the Rng of the observer of the given name: the input
+/// seeds, tagged with a non-zero word, and with the bytes of the
+/// name, so that each observer's Rng differs from that of the editor,
+/// and from those of the other observers.  Hence, observing does not
+/// perturb editing, and an observer's choices do not depend on the
+/// other observers, nor on their order.
+pub fn observer_seeds(seeds:&Vec<usize>, name:&str) -> Vec<usize> {
+  let mut obs_seeds = seeds.clone();
+  obs_seeds.push(OBSERVER_SEED_TAG);
+  for b in name.bytes() { obs_seeds.push(b as usize) };
+  obs_seeds
+}
+
+/// The seeds of the Rng of the demand (See
+/// `LabParams::demand_change`): the input seeds, tagged with a
+/// non-zero word (unlike that of `observer_seeds`), so that the
+/// demands do not depend on the editor, nor on the observers.
+pub fn demand_seeds(seeds:&Vec<usize>) -> Vec<usize> {
+  let mut demand_seeds = seeds.clone();
+  demand_seeds.push(DEMAND_SEED_TAG);
+  demand_seeds
+}
+
+fn get_sample_gen
+  <'a,
+   R:LabRngGen,
+   Input:Clone+Debug+Eq,
    EditSt,
-   Output:Eq+Debug,
-   Editor:Generate<Input>+Edit<Input,EditSt>,
-   Archivist:ComputeDemand<Input,Output>> 
-  (params:&LabParams) 
-   -> LabState<rand::StdRng,Input,EditSt,Output,Editor,Archivist> 
+   Output:Debug> 
+  (fns:&'a LabFns<Input,EditSt,Output>, params:&LabParams) 
+   -> LabState<'a,R,Input,EditSt,Output> 
 {
-  // Create empty DCG; TODO-Minor-- Make the API for this better.
-  let _ = init_dcg(); assert!(engine_is_dcg());
-  let empty_dcg = use_engine(Engine::Naive); // TODO-Minor: Rename this operation: "engine_swap" or something 
-  let rng = SeedableRng::from_seed(params.sample_params.input_seeds.as_slice());
+  let rng : R = R::from_seeds(&params.sample_params.input_seeds);
+  let obs_rngs : Vec<Box<R>> = fns.observers().iter().map(|observer| {
+    Box::new(R::from_seeds(&observer_seeds(&params.sample_params.input_seeds, &observer.name)))
+  }).collect();
+  let demand_rng : R = R::from_seeds(&demand_seeds(&params.sample_params.input_seeds));
   //let editst_init = Editor::edit_init(&mut rng, & params.sample_params.generate_params);
+  let engine_states = params.engines.iter().map(|config| {
+    let shadowed = *config != EngineConfig::Naive;
+    LabEngineState{
+      config: config.clone(),
+      input:  None,
+      engine: engine_of_config(config),
+      validate_shadow: if shadowed && params.sample_params.validate_output && fns.output_view_forces() {
+        Some(LabShadowState{ engine: engine_of_config(config), input: None })
+      } else { None },
+      observer_shadows: if shadowed {
+        fns.observers().iter().map(|_| LabShadowState{ engine: engine_of_config(config), input: None }).collect()
+      } else { vec![] },
+      output: PhantomData,
+    }
+  }).collect();
   LabState{
+    fns,
     params:params.clone(),
+    init_rng:Box::new(rng.clone()),
     rng:Box::new(rng),
-    dcg_state:LabEngineState{
-      input:  None,
-      engine: empty_dcg, // empty DCG      
-      output: PhantomData, inputdist: PhantomData, computer: PhantomData,      
-    },
-    naive_state:LabEngineState{
-      input:  None,
-      engine: Engine::Naive, // A constant
-      output: PhantomData, inputdist: PhantomData, computer: PhantomData,
-    },
+    obs_rngs,
+    demand_rng:Box::new(demand_rng),
+    engine_states,
     change_batch_num: 0,
+    edit_batch_num: 0,
+    demand: params.sample_params.demand,
     samples:vec![],
+    errors:vec![],
   }
 }
 
-/// Advances the LabState forward by one sample of each engine.  For
-/// each engine, we process the current input (either generating it,
-/// or editing it) and we compute a new output over this processed input.
-/// Optionally, we compare the outputs of the engines for equality.
-impl<Input:Clone+Debug,EditSt,Output:Eq+Debug,
-     Editor:Generate<Input>+Edit<Input,EditSt>,
-     Archivist:ComputeDemand<Input,Output>>
-  SampleGen for LabState<rand::StdRng,Input,EditSt,Output,Editor,Archivist> {
+/// Advances the LabState forward by one sample of each engine
+/// configuration.  For each, we process the current input (either
+/// generating it, or editing it) and we compute a new output over
+/// this processed input.  Optionally, we compare the outputs of the
+/// engines to that of the reference engine, for equivalence (See
+/// `OutputEquiv`).  We take the view of the measured output, unless
+/// the view may force it; then, but for the naive engine, we take the
+/// view of the output of a shadow of the engine (See
+/// `LabShadowState`), and so validate the shadow's output, whose DCG
+/// has a different history than the measured one.
+///
+/// Likewise, each observer of the lab (See `labobs`) observes the
+/// output in its own shadow of the engine (but for the naive engine),
+/// with its own Rng (See `observer_seeds`), so that its observation
+/// is timed separately from those of the other observers, and does
+/// not perturb the measured engine.  Its shadow does retain the
+/// effects of its own prior observations, across batches; hence,
+/// each observation depends on the prior observations of the same
+/// observer, but not on the other observers, nor on their order.
+/// Demand-only batches draw their demand from yet another Rng (See
+/// `demand_seeds`).  When the editor fails (See `EditError`), we
+/// record the failure, and stop sampling.
+impl<'a,R:LabRngGen,Input:'a+Clone+Debug+Eq,EditSt:'a,Output:'a+Debug>
+  SampleGen for LabState<'a,R,Input,EditSt,Output> {
     fn sample (self:&mut Self) -> Option<Sample> {
-      if self.change_batch_num > self.params.change_batch_loopc {
+      if self.change_batch_num > self.params.change_batch_loopc || self.errors.len() > 0 {
         None 
       } else { // Collect the next sample, for each engine, using get_engine_sample.
-        let mut dcg_state = LabEngineState{ input: None, engine: Engine::Naive, 
-                                             output: PhantomData, inputdist: PhantomData, computer: PhantomData };
-        swap(&mut dcg_state, &mut self.dcg_state );
-        let mut naive_state = LabEngineState{ input: None, engine: Engine::Naive, 
-                                               output: PhantomData, inputdist: PhantomData, computer: PhantomData };
-        swap(&mut naive_state, &mut self.naive_state );
-
-        // Run Naive Version
-        //println!("Naive - - - - - ({:?} / {:?})", self.change_batch_num, self.params.change_batch_loopc );
-        let _ = use_engine(Engine::Naive); assert!(engine_is_naive());
-        let mut rng = self.rng.clone(); // Restore Rng
-        let (naive_output, naive_input_edited, naive_editst, naive_sample) = 
-          get_engine_sample::<rand::StdRng,Input,EditSt,Output,Editor,Archivist>
-          (&mut rng, &self.params.sample_params, naive_state.input);
-        self.naive_state.input = Some((naive_input_edited, naive_editst)); // Save the input and input-editing state
-
-        // Run DCG Version
-        //println!("DCG - - - - - ");
-        let _ = use_engine(dcg_state.engine); // Restore saved DCG
-        assert!(engine_is_dcg()); // This really is the DCG version
-        let mut rng = self.rng.clone(); // Restore Rng
-        let (dcg_output, dcg_input_edited, dcg_editst, dcg_sample) = 
-          get_engine_sample::<rand::StdRng,Input,EditSt,Output,Editor,Archivist>
-          (&mut rng, &self.params.sample_params, dcg_state.input);
-        self.dcg_state.engine = use_engine(Engine::Naive); // Swap out the DCG
-        self.dcg_state.input = Some((dcg_input_edited, dcg_editst)); // Save the input and input-editing state
-        
-        // Save the Rng for the next sample.
-        self.rng = Box::new(*rng);
-
-        // Compare the two outputs for equality
-        let output_valid = if self.params.sample_params.validate_output { 
-          Some ( dcg_output == naive_output )
-        } else { None } ;
+        // Does this batch edit the input, or change only the demand?
+        let demand_only = match self.params.demand_change {
+          Some(ref dc) => dc.period > 0 && self.change_batch_num > 0 && self.change_batch_num % dc.period == 0,
+          None => false,
+        };
+        if demand_only {
+          let max_demand = self.params.demand_change.as_ref().unwrap().max_demand;
+          // (The full range of usize has no exclusive upper bound.)
+          self.demand = if max_demand == usize::max_value() {
+            self.demand_rng.next_u64() as usize
+          } else {
+            self.demand_rng.gen_range(0, max_demand + 1)
+          };
+        } else if self.change_batch_num > 0 {
+          self.edit_batch_num += 1;
+        };
+        let mut engine_samples = vec![];
+        let mut inputs  : Vec<Input>  = vec![];
+        // The views of the outputs, when validating them (See `OutputEquiv`).
+        let mut views   : Vec<Box<Any>> = vec![];
+        // The observations of the outputs, per engine (See `labobs`).
+        let mut observations : Vec<Vec<Box<Any>>> = vec![];
+        let mut next_rng = None;
+        let mut next_obs_rngs = None;
+        let fns = self.fns;
+        let validate_output = self.params.sample_params.validate_output;
+        for state in self.engine_states.iter_mut() {
+          //println!("{} - - - - - ({:?} / {:?})", state.config.name(), self.change_batch_num, self.params.change_batch_loopc );
+          let sample_params = match state.config {
+            EngineConfig::DCGNoReflect => SampleParams{
+              reflect_trace: false,
+              reflect_dcg: false,
+              demand: self.demand,
+              .. self.params.sample_params.clone()
+            },
+            _ => SampleParams{
+              demand: self.demand,
+              .. self.params.sample_params.clone()
+            },
+          };
+          let from_scratch = state.config == EngineConfig::DCGFromScratch;
+          match state.config {
+            EngineConfig::Naive => {
+              let _ = use_engine(Engine::Naive); assert!(engine_is_naive());
+            },
+            EngineConfig::DCG |
+            EngineConfig::DCGNoReflect => {
+              let engine = replace(&mut state.engine, Engine::Naive);
+              let _ = use_engine(engine); // Restore saved DCG
+              assert!(engine_is_dcg()); // This really is the DCG version
+            },
+            EngineConfig::DCGFromScratch => {
+              let _ = use_engine(empty_dcg());
+              assert!(engine_is_dcg());
+            },
+          };
+          // Restore Rng; from scratch, we replay every prior batch of edits.
+          let (mut rng, input, replay) = if from_scratch { 
+            (self.init_rng.clone(), None, self.edit_batch_num)
+          } else {
+            (self.rng.clone(), state.input.take(), 0)
+          };
+          let pre_rng = rng.clone();
+          let (output, input_edited, editst, mut engine_sample) = 
+            match get_engine_sample::<Input,EditSt,Output>
+            (fns, &mut *rng, &sample_params, &state.config, input, replay, !demand_only) {
+              Ok(s) => s,
+              Err(error) => {
+                let _ = use_engine(Engine::Naive); // Swap out the engine
+                self.errors.push(LabError{
+                  batch_name: self.change_batch_num,
+                  engine: state.config.clone(),
+                  error,
+                });
+                return None
+              }
+            };
+          let engine = use_engine(Engine::Naive); // Swap out the engine
+          // Run each observer, in order, each in its own shadow; the
+          // naive engine has no state to perturb.
+          let mut obs_rngs = self.obs_rngs.clone();
+          let mut obs_vals = vec![];
+          for (i, observer) in fns.observers().iter().enumerate() {
+            let obs_rng = &mut obs_rngs[i];
+            let mut observe = |output:&Output| {
+              ns(name_of_str("observe"), || {
+                get_engine_metrics(&sample_params, || (observer.observe)(output, &mut **obs_rng as &mut Rng, &sample_params))
+              })
+            };
+            let (obs, metrics) = if state.observer_shadows.len() == 0 { observe(&output) } else {
+              let mut rng = pre_rng.clone();
+              match with_shadow(fns, &mut state.observer_shadows[i], &state.config, &mut *rng, &sample_params,
+                                replay, !demand_only, observe) {
+                Ok(obs) => obs,
+                Err(error) => {
+                  self.errors.push(LabError{
+                    batch_name: self.change_batch_num,
+                    engine: state.config.clone(),
+                    error,
+                  });
+                  return None
+                }
+              }
+            };
+            engine_sample.observations.push(Observation{
+              name: observer.name.clone(),
+              metrics,
+              valid: None,
+            });
+            obs_vals.push(obs);
+          };
+          observations.push(obs_vals);
+          next_obs_rngs = Some(obs_rngs);
+          // Take a view that may force the output in the shadow; the
+          // naive engine, and views that force nothing, perturb nothing.
+          if validate_output {
+            let view = match state.validate_shadow {
+              None => fns.output_view(&output),
+              Some(ref mut shadow) => {
+                let mut rng = pre_rng.clone();
+                match with_shadow(fns, shadow, &state.config, &mut *rng, &sample_params,
+                                  replay, !demand_only, |output| fns.output_view(output)) {
+                  Ok(view) => view,
+                  Err(error) => {
+                    self.errors.push(LabError{
+                      batch_name: self.change_batch_num,
+                      engine: state.config.clone(),
+                      error,
+                    });
+                    return None
+                  }
+                }
+              }
+            };
+            views.push(view)
+          };
+          inputs.push(input_edited.clone());
+          if !from_scratch {
+            state.engine = engine;
+            state.input = Some((input_edited, editst)); // Save the input and input-editing state
+            next_rng = Some(rng);
+          };
+          drop(output);
+          engine_samples.push(engine_sample);
+        };
+
+        // Save the Rngs for the next sample.
+        match next_rng {
+          Some(rng) => self.rng = rng,
+          None => (),
+        };
+        match next_obs_rngs {
+          Some(rngs) => self.obs_rngs = rngs,
+          None => (),
+        };
+
+        // Compare each input to the reference input, for equality;
+        // this detects editors that are nondeterministic, or engine-dependent.
+        let reference = self.params.engines.iter().position(|c| *c == self.params.reference_engine);
+        let input_valid = match reference {
+          Some(r) if self.params.sample_params.validate_input => {
+            for (engine_sample, input) in engine_samples.iter_mut().zip(inputs.iter()) {
+              engine_sample.input_valid = Some( *input == inputs[r] );
+            };
+            Some( inputs.iter().all(|input| *input == inputs[r]) )
+          },
+          _ => None,
+        };
+
+        // Compare each output to the reference output, for equivalence
+        let output_valid = match reference {
+          Some(r) if validate_output => {
+            for (engine_sample, view) in engine_samples.iter_mut().zip(views.iter()) {
+              engine_sample.output_valid = Some( fns.output_equiv(&**view, &*views[r]) );
+            };
+            Some( engine_samples.iter().all(|s| s.output_valid == Some(true)) )
+          },
+          _ => None,
+        };
+
+        // Compare each observation to the reference engine's, for equality
+        match reference {
+          Some(r) if validate_output => {
+            for (engine_sample, obs_vals) in engine_samples.iter_mut().zip(observations.iter()) {
+              for (i, (observation, obs)) in engine_sample.observations.iter_mut().zip(obs_vals.iter()).enumerate() {
+                observation.valid = Some( (fns.observers()[i].equiv)(&**obs, &*observations[r][i]) );
+              }
+            }
+          },
+          _ => (),
+        };
 
         let sample = Sample{
           //params:self.params.sample_params.clone(),
           batch_name:self.change_batch_num,
-          dcg_sample,
-          naive_sample,
+          demand_only,
+          demand:self.demand,
+          engine_samples,
+          input_valid,
           output_valid,
         };
         self.change_batch_num += 1;
@@ -196,28 +548,110 @@ impl<Input:Clone+Debug,EditSt,Output:Eq+Debug,
     }
   }
 
+/// Runs a lab, given its editor and archivist as values (See
+/// `LabFns`), with the random number generator that the parameters
+/// choose (See `LabParams::rng`).
+fn run_lab_fns<Input:Clone+Debug+Eq,EditSt,Output:Debug>
+  (fns:&LabFns<Input,EditSt,Output>, params:&LabParams, progress:&mut LabProgress) -> LabResults 
+{
+  match params.rng {
+    LabRng::Pcg32          => run_lab_fns_rng::<Pcg32,Input,EditSt,Output>(fns, params, progress),
+    LabRng::XorShift64Star => run_lab_fns_rng::<XorShift64Star,Input,EditSt,Output>(fns, params, progress),
+  }
+}
+
+/// Gives each sample to the progress, as soon as it is collected
+/// (See `LabProgress`), and stops when the progress says to.
+fn run_lab_fns_rng<R:LabRngGen,Input:Clone+Debug+Eq,EditSt,Output:Debug>
+  (fns:&LabFns<Input,EditSt,Output>, params:&LabParams, progress:&mut LabProgress) -> LabResults 
+{
+  let time_start = time::precise_time_ns();
+  let mut st = get_sample_gen::<R,Input,EditSt,Output>(fns, params);
+  let mut stopped = None;
+  loop {
+    let sample = (&mut st).sample();
+    match sample {
+      Some(s) => {
+        let prog = Progress{
+          batch_name:  s.batch_name,
+          batch_count: params.change_batch_loopc + 1,
+          elapsed_ns:  time::precise_time_ns() - time_start,
+        };
+        let flow = progress.on_sample(&prog, &s);
+        st.samples.push(s);
+        match flow {
+          Flow::Continue => continue,
+          Flow::Stop(reason) => { stopped = Some(reason); break },
+        }
+      },
+      None => break,
+    }
+  };
+  for error in st.errors.iter() { progress.on_error(error) };
+  return LabResults {
+    rng:     params.rng.clone(),
+    samples: st.samples,
+    errors:  st.errors,
+    stopped,
+  }
+}
+
 /// Lab experiment implementation: Implements the LabDef trait for any
 /// LabArchivist instantiation.
-impl<Input:Clone+Debug,EditSt,Output:Eq+Debug,
+impl<Input:Clone+Debug+Eq,EditSt,Output:Debug,
      Editor:'static+Generate<Input>+Edit<Input,EditSt>,
-     Archivist:'static+ComputeDemand<Input,Output>>
-  Lab for LabDef<Input,EditSt,Output,Editor,Archivist> {
+     Archivist:'static+ComputeCtx<Input,Output>,
+     Equiv:'static+OutputEquiv<Output>>
+  Lab for LabDef<Input,EditSt,Output,Editor,Archivist,Equiv> {
     fn name(self:&Self) -> Name { self.identity.clone() }
     fn url(self:&Self) -> &Option<String> { &self.url }
-    fn run(self:&Self, params:&LabParams) -> LabResults 
-    {            
-      let mut st = get_sample_gen::<Input,EditSt,Output,Editor,Archivist>(params);
-      loop {
-        //println!("{:?}", self.name());
-        let sample = (&mut st).sample();
-        //println!("{:?}", sample);        
-        match sample {
-          Some(s) => {st.samples.push(s); continue},
-          None => break,
-        }
-      };
-      return LabResults {
-        samples: st.samples,
-      }
+    fn meta(self:&Self) -> &LabMeta { &self.meta }
+    fn knobs(self:&Self) -> &LabKnobs { &self.knobs }
+    fn run_with(self:&Self, params:&LabParams, progress:&mut LabProgress) -> LabResults {
+      run_lab_fns(self, params, progress)
+    }
+  }
+
+/// Lab experiment implementation, for labs built from closures (See `LabBuilder`).
+impl<Input:Clone+Debug+Eq,EditSt,Output:Debug>
+  Lab for LabFnDef<Input,EditSt,Output> {
+    fn name(self:&Self) -> Name { self.identity.clone() }
+    fn url(self:&Self) -> &Option<String> { &self.url }
+    fn meta(self:&Self) -> &LabMeta { &self.meta }
+    fn knobs(self:&Self) -> &LabKnobs { &self.knobs }
+    fn run_with(self:&Self, params:&LabParams, progress:&mut LabProgress) -> LabResults {
+      run_lab_fns(self, params, progress)
     }
   }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// The first draws of the editor's, two observers', and the
+  /// demand's Rngs.
+  fn first_draws<R:LabRngGen>(seeds:&Vec<usize>) -> Vec<u64> {
+    let mut rngs : Vec<R> = vec![ R::from_seeds(seeds),
+                                  R::from_seeds(&observer_seeds(seeds, "last")),
+                                  R::from_seeds(&observer_seeds(seeds, "random-index")),
+                                  R::from_seeds(&demand_seeds(seeds)) ];
+    rngs.iter_mut().map(|rng| rng.next_u64()).collect()
+  }
+
+  fn all_distinct(xs:&Vec<u64>) -> bool {
+    let mut ys = xs.clone();
+    ys.sort();
+    ys.dedup();
+    ys.len() == xs.len()
+  }
+
+  /// The Rngs of the editor, of each observer, and of the demand
+  /// differ, for each generator.
+  #[test]
+  fn observer_seeds_are_distinct() {
+    for seeds in vec![ vec![], vec![0], vec![0,0], vec![1,2,3] ] {
+      assert!( all_distinct(&first_draws::<Pcg32>(&seeds)) );
+      assert!( all_distinct(&first_draws::<XorShift64Star>(&seeds)) );
+    }
+  }
+}
@@ -0,0 +1,471 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+
+use labdef::{Sample, EngineSample, EngineConfig};
+use labcheck::roots_of_traces;
+use adapton::engine::reflect::{DCG, Loc, Node, Path, Val, trace, succs_of_node, string_of_name, string_of_loc};
+
+/// Counts of each case of `trace::Effect` (See
+/// `adapton::engine::reflect::trace`), e.g., in a trace forest.
+#[derive(Clone,Debug,Default,PartialEq,Eq)]
+pub struct EffectCnt {
+  pub clean_rec:    usize,
+  pub clean_eval:   usize,
+  pub clean_edge:   usize,
+  pub dirty:        usize,
+  pub remove:       usize,
+  pub alloc_fresh:  usize,
+  pub alloc_exists: usize,
+  pub force_miss:   usize,
+  pub force_hit:    usize,
+  pub force_refget: usize,
+}
+
+impl EffectCnt {
+  /// Counts one more effect.
+  pub fn add_effect(self:&mut Self, eff:&trace::Effect) {
+    match *eff {
+      trace::Effect::CleanRec  => self.clean_rec  += 1,
+      trace::Effect::CleanEval => self.clean_eval += 1,
+      trace::Effect::CleanEdge => self.clean_edge += 1,
+      trace::Effect::Dirty     => self.dirty      += 1,
+      trace::Effect::Remove    => self.remove     += 1,
+      trace::Effect::Alloc(trace::AllocCase::LocFresh,_)     => self.alloc_fresh  += 1,
+      trace::Effect::Alloc(trace::AllocCase::LocExists,_)    => self.alloc_exists += 1,
+      trace::Effect::Force(trace::ForceCase::CompCacheMiss)  => self.force_miss   += 1,
+      trace::Effect::Force(trace::ForceCase::CompCacheHit)   => self.force_hit    += 1,
+      trace::Effect::Force(trace::ForceCase::RefGet)         => self.force_refget += 1,
+    }
+  }
+  /// Adds the counts of another `EffectCnt` to these counts.
+  pub fn add(self:&mut Self, other:&EffectCnt) {
+    self.clean_rec    += other.clean_rec;
+    self.clean_eval   += other.clean_eval;
+    self.clean_edge   += other.clean_edge;
+    self.dirty        += other.dirty;
+    self.remove       += other.remove;
+    self.alloc_fresh  += other.alloc_fresh;
+    self.alloc_exists += other.alloc_exists;
+    self.force_miss   += other.force_miss;
+    self.force_hit    += other.force_hit;
+    self.force_refget += other.force_refget;
+  }
+  /// Cache hits: forces of computations whose cached values we reuse.
+  pub fn hits(self:&Self) -> usize { self.force_hit }
+  /// Cache misses: forces of computations that we (re-)evaluate.
+  pub fn misses(self:&Self) -> usize { self.force_miss }
+  /// Allocations, both of fresh and of existing locations.
+  pub fn allocs(self:&Self) -> usize { self.alloc_fresh + self.alloc_exists }
+  /// Cleaning: all three cases of cleaning.
+  pub fn cleans(self:&Self) -> usize { self.clean_rec + self.clean_eval + self.clean_edge }
+}
+
+/// The counts of a trace forest, including all nested traces.
+pub fn effect_cnt_of_traces(traces:&Vec<trace::Trace>) -> EffectCnt {
+  let mut cnt = EffectCnt::default();
+  for tr in traces.iter() {
+    cnt.add_effect(&tr.effect);
+    cnt.add(&effect_cnt_of_traces(&tr.extent));
+  };
+  cnt
+}
+
+/// A string for a namespace path, e.g., `compute/tree_of_list`; the
+/// root namespace is the empty string.
+pub fn string_of_path_prefix(path:&Path, len:usize) -> String {
+  path.iter().take(len).map(string_of_name).collect::<Vec<_>>().join("/")
+}
+
+/// The counts of one namespace: all traces whose location's path has
+/// this namespace as a prefix.
+#[derive(Clone,Debug)]
+pub struct NsCnt {
+  pub namespace: String,
+  /// The number of names in the namespace path.
+  pub depth:     usize,
+  pub cnt:       EffectCnt,
+  /// The deepest nesting of any trace in this namespace (See `DepthStats`).
+  pub max_trace_depth: usize,
+  /// The longest chain of nested forces that ends in this namespace.
+  pub max_force_chain: usize,
+}
+
+fn is_force(eff:&trace::Effect) -> bool {
+  match *eff { trace::Effect::Force(_) => true, _ => false }
+}
+
+fn ns_cnts_rec(traces:&Vec<trace::Trace>, trace_depth:usize, force_chain:usize,
+               table:&mut BTreeMap<String,NsCnt>) {
+  for tr in traces.iter() {
+    let path = &tr.edge.succ.loc.path;
+    let force_chain = if is_force(&tr.effect) { force_chain + 1 } else { force_chain };
+    for len in 0..(path.len() + 1) {
+      let namespace = string_of_path_prefix(path, len);
+      let entry = table.entry(namespace.clone()).or_insert(
+        NsCnt{ namespace, depth:len, cnt:EffectCnt::default(), 
+               max_trace_depth:0, max_force_chain:0 });
+      entry.cnt.add_effect(&tr.effect);
+      if trace_depth + 1 > entry.max_trace_depth { entry.max_trace_depth = trace_depth + 1 };
+      if force_chain > entry.max_force_chain { entry.max_force_chain = force_chain };
+    };
+    ns_cnts_rec(&tr.extent, trace_depth + 1, force_chain, table)
+  }
+}
+
+/// Aggregates a trace forest by namespace: For each prefix of the
+/// path of each traced location (See `Loc::path`), counts the effects
+/// within that namespace.  Sorted by namespace, so each namespace
+/// precedes the namespaces that it contains.
+pub fn ns_cnts_of_traces(traces:&Vec<trace::Trace>) -> Vec<NsCnt> {
+  let mut table = BTreeMap::new();
+  ns_cnts_rec(traces, 0, 0, &mut table);
+  table.into_iter().map(|(_, ns_cnt)| ns_cnt).collect()
+}
+
+/// The shape of a trace forest.  Deep nesting of forces corresponds
+/// to deep recursion in the engine (which is why `main` runs the labs
+/// with a large stack), so these statistics warn of naming strategies
+/// that produce dangerously deep chains of dependencies.
+#[derive(Clone,Debug,Default)]
+pub struct DepthStats {
+  /// The deepest nesting of traces; zero for an empty forest.
+  pub max_depth:   usize,
+  /// The number of traces at each level of nesting, outermost first.
+  pub widths:      Vec<usize>,
+  /// The longest chain of nested forces.
+  pub force_chain: usize,
+}
+
+impl DepthStats {
+  /// The width of the widest level of nesting.
+  pub fn max_width(self:&Self) -> usize {
+    self.widths.iter().fold(0, |m, w| if *w > m { *w } else { m })
+  }
+}
+
+fn depth_stats_rec(traces:&Vec<trace::Trace>, depth:usize, force_chain:usize, stats:&mut DepthStats) {
+  for tr in traces.iter() {
+    let force_chain = if is_force(&tr.effect) { force_chain + 1 } else { force_chain };
+    if stats.widths.len() <= depth { stats.widths.push(0) };
+    stats.widths[depth] += 1;
+    if depth + 1 > stats.max_depth { stats.max_depth = depth + 1 };
+    if force_chain > stats.force_chain { stats.force_chain = force_chain };
+    depth_stats_rec(&tr.extent, depth + 1, force_chain, stats)
+  }
+}
+
+/// The depth statistics of a trace forest (See `DepthStats`).
+pub fn depth_stats_of_traces(traces:&Vec<trace::Trace>) -> DepthStats {
+  let mut stats = DepthStats::default();
+  depth_stats_rec(traces, 0, 0, &mut stats);
+  stats
+}
+
+/// A memoized computation that (re-)evaluates, perhaps repeatedly,
+/// over the batches of a lab.  Each re-evaluation is either a cache
+/// miss (`Force(CompCacheMiss)`) or a cleaning (`CleanEval`).
+#[derive(Clone,Debug)]
+pub struct HotSpot<K> {
+  /// A location, or the string of a namespace.
+  pub key:      K,
+  /// Total evaluations, over all batches.
+  pub evals:    usize,
+  /// Evaluations after the first batch, i.e., during updates.
+  pub re_evals: usize,
+  /// The batches that evaluate this computation, in order.
+  pub batches:  Vec<usize>,
+}
+
+fn hot_spots_rec(batch_name:usize, traces:&Vec<trace::Trace>, 
+                 table:&mut HashMap<Loc,HotSpot<Loc>>) {
+  for tr in traces.iter() {
+    match tr.effect {
+      trace::Effect::Force(trace::ForceCase::CompCacheMiss) |
+      trace::Effect::CleanEval => {
+        let loc = &tr.edge.succ.loc;
+        let spot = table.entry(loc.clone()).or_insert(
+          HotSpot{ key:loc.clone(), evals:0, re_evals:0, batches:vec![] });
+        spot.evals += 1;
+        if batch_name > 0 { spot.re_evals += 1 };
+        if spot.batches.last() != Some(&batch_name) { spot.batches.push(batch_name) };
+      },
+      _ => (),
+    };
+    hot_spots_rec(batch_name, &tr.extent, table)
+  }
+}
+
+fn rank_hot_spots<K>(spots:&mut Vec<HotSpot<K>>, key_string:&Fn(&K) -> String) {
+  spots.sort_by(|s1, s2| {
+    match (s2.re_evals, s2.evals).cmp(&(s1.re_evals, s1.evals)) {
+      Ordering::Equal => key_string(&s1.key).cmp(&key_string(&s2.key)),
+      ord => ord,
+    }
+  })
+}
+
+/// Groups hot spots by namespace (the path of each location), and
+/// ranks both groupings.
+fn rank_hot_spots_table(table:HashMap<Loc,HotSpot<Loc>>) -> (Vec<HotSpot<Loc>>, Vec<HotSpot<String>>) {
+  let mut by_ns : BTreeMap<String, HotSpot<String>> = BTreeMap::new();
+  for spot in table.values() {
+    let ns = string_of_path_prefix(&spot.key.path, spot.key.path.len());
+    let ns_spot = by_ns.entry(ns.clone()).or_insert(
+      HotSpot{ key:ns, evals:0, re_evals:0, batches:vec![] });
+    ns_spot.evals    += spot.evals;
+    ns_spot.re_evals += spot.re_evals;
+    for b in spot.batches.iter() {
+      if !ns_spot.batches.contains(b) { ns_spot.batches.push(*b) }
+    };
+    ns_spot.batches.sort();
+  };
+  let mut by_loc : Vec<HotSpot<Loc>> = table.into_iter().map(|(_, spot)| spot).collect();
+  let mut by_ns  : Vec<HotSpot<String>> = by_ns.into_iter().map(|(_, spot)| spot).collect();
+  rank_hot_spots(&mut by_loc, &|l:&Loc| string_of_loc(l));
+  rank_hot_spots(&mut by_ns,  &|s:&String| s.clone());
+  (by_loc, by_ns)
+}
+
+/// Hot spots: Counts the evaluations of each location, over the
+/// archivist traces of all samples of the given engine.  Returns the
+/// counts by location, and by namespace (the path of each location),
+/// each ranked by re-evaluations (most first).
+pub fn hot_spots(samples:&Vec<Sample>, engine:&EngineConfig) 
+                 -> (Vec<HotSpot<Loc>>, Vec<HotSpot<String>>)
+{
+  let mut table = HashMap::new();
+  for sample in samples.iter() {
+    match sample.engine_sample(engine) {
+      None => (),
+      Some(s) => hot_spots_rec(sample.batch_name, &s.compute_output.reflect_traces, &mut table),
+    }
+  };
+  rank_hot_spots_table(table)
+}
+
+/// For one batch, the dirtying footprint of the editor, versus the
+/// cleaning work of the archivist.
+#[derive(Clone,Debug)]
+pub struct DirtyFootprint {
+  pub batch_name:   usize,
+  /// Edges that the editor dirtied (`Dirty`).
+  pub dirtied:      usize,
+  /// Edges that the archivist cleaned, without re-evaluation (`CleanEdge` and `CleanRec`).
+  pub cleaned:      usize,
+  /// Edges whose cleaning forced re-evaluation (`CleanEval`).
+  pub re_evaluated: usize,
+}
+
+impl DirtyFootprint {
+  /// The fraction of dirtied edges cleaned without re-evaluation.
+  pub fn clean_ratio(self:&Self) -> f64 {
+    if self.dirtied == 0 { 0.0 } else { self.cleaned as f64 / self.dirtied as f64 }
+  }
+  /// The fraction of dirtied edges whose cleaning forced re-evaluation.
+  pub fn eval_ratio(self:&Self) -> f64 {
+    if self.dirtied == 0 { 0.0 } else { self.re_evaluated as f64 / self.dirtied as f64 }
+  }
+}
+
+/// The dirtying footprint of each batch of the given engine, from its
+/// effect counts (See `EngineMetrics::effect_cnt`), but those that
+/// change only the demand (See `Sample::demand_only`), whose editor
+/// dirties nothing.  A precise change propagation cleans most dirtied
+/// edges without re-evaluation.
+pub fn dirty_footprints(samples:&Vec<Sample>, engine:&EngineConfig) -> Vec<DirtyFootprint> {
+  samples.iter().filter(|sample| !sample.demand_only).filter_map(|sample| {
+    sample.engine_sample(engine).map(|s| {
+      DirtyFootprint{
+        batch_name:   sample.batch_name,
+        dirtied:      s.process_input.effect_cnt.dirty,
+        cleaned:      s.compute_output.effect_cnt.clean_edge + s.compute_output.effect_cnt.clean_rec,
+        re_evaluated: s.compute_output.effect_cnt.clean_eval,
+      }
+    })
+  }).collect()
+}
+
+/// Pushes the locations of the articulations within a reflected value.
+pub fn locs_of_val(val:&Val, locs:&mut Vec<Loc>) {
+  match *val {
+    Val::Constr(_, ref vs) | 
+    Val::Tuple(ref vs) | 
+    Val::Vec(ref vs) => for v in vs.iter() { locs_of_val(v, locs) },
+    Val::Struct(_, ref fs) => for &(_, ref v) in fs.iter() { locs_of_val(v, locs) },
+    Val::Art(ref loc, _) => locs.push(loc.clone()),
+    Val::Const(_) | Val::Name(_) | Val::ValTODO => (),
+  }
+}
+
+/// The locations of a DCG reachable from the given roots, via
+/// successor edges (both forces and allocations), and via the
+/// articulations within the values of nodes.
+pub fn reachable_locs(dcg:&DCG, roots:&Vec<Loc>) -> HashMap<Loc,()> {
+  let mut reached = HashMap::new();
+  let mut stack = roots.clone();
+  while let Some(loc) = stack.pop() {
+    if reached.contains_key(&loc) { continue };
+    match dcg.table.get(&loc) {
+      None => (), // Dangling; See `labcheck::check_dcg`.
+      Some(node) => {
+        if let Some(succs) = succs_of_node(node) {
+          for succ in succs.iter() { stack.push(succ.loc.clone()) }
+        };
+        match *node {
+          Node::Pure(ref n) => locs_of_val(&n.value, &mut stack),
+          Node::Ref(ref n)  => locs_of_val(&n.value, &mut stack),
+          Node::Comp(ref n) => match n.value {
+            None => (),
+            Some(ref v) => locs_of_val(v, &mut stack),
+          },
+        }
+      }
+    };
+    reached.insert(loc, ());
+  };
+  reached
+}
+
+/// The garbage of the DCG at the end of one batch: the nodes that are
+/// unreachable from the input, the output and the roots of the
+/// archivist's traces, but which remain in the DCG's table.
+#[derive(Clone,Debug)]
+pub struct Garbage {
+  pub batch_name:  usize,
+  /// The number of nodes in the DCG's table.
+  pub nodes:       usize,
+  /// The unreachable locations, in order of their strings.
+  pub garbage:     Vec<Loc>,
+  /// The number of unreachable locations that were not unreachable in
+  /// the prior batch.
+  pub new_garbage: usize,
+  /// The number of unreachable locations in each namespace (See `Loc::path`).
+  pub by_ns:       Vec<(String, usize)>,
+}
+
+/// The garbage of each batch of the given engine (See `Garbage`);
+/// requires both reflected DCGs and reflected traces.  Growth across
+/// batches indicates a space leak in long-running sessions.
+pub fn garbage_of_samples(samples:&Vec<Sample>, engine:&EngineConfig) -> Vec<Garbage> {
+  let mut result : Vec<Garbage> = vec![];
+  let mut prev_garbage : HashMap<Loc,()> = HashMap::new();
+  for sample in samples.iter() {
+    let s = match sample.engine_sample(engine) { None => continue, Some(s) => s };
+    let dcg = match s.compute_output.reflect_dcg { None => continue, Some(ref dcg) => dcg };
+    let mut roots = roots_of_traces(&s.compute_output.reflect_traces);
+    if let Some(ref input)  = s.input  { locs_of_val(input,  &mut roots) };
+    if let Some(ref output) = s.output { locs_of_val(output, &mut roots) };
+    let reached = reachable_locs(dcg, &roots);
+    let mut garbage : Vec<Loc> = dcg.table.keys()
+      .filter(|l| !reached.contains_key(*l)).cloned().collect();
+    garbage.sort_by_key(|l| string_of_loc(l));
+    let new_garbage = garbage.iter().filter(|l| !prev_garbage.contains_key(*l)).count();
+    let mut by_ns : BTreeMap<String,usize> = BTreeMap::new();
+    for loc in garbage.iter() {
+      *by_ns.entry(string_of_path_prefix(&loc.path, loc.path.len())).or_insert(0) += 1;
+    };
+    prev_garbage = garbage.iter().map(|l| (l.clone(), ())).collect();
+    result.push(Garbage{
+      batch_name: sample.batch_name,
+      nodes: dcg.table.len(),
+      garbage,
+      new_garbage,
+      by_ns: by_ns.into_iter().collect(),
+    })
+  };
+  result
+}
+
+fn median_by(cnts:&Vec<&EffectCnt>, field:&Fn(&EffectCnt) -> usize) -> usize {
+  let mut xs : Vec<usize> = cnts.iter().map(|c| field(c)).collect();
+  xs.sort();
+  if xs.len() == 0 { 0 } else { xs[xs.len() / 2] }
+}
+
+/// The field-wise median of some effect counts.
+pub fn median_effect_cnt(cnts:&Vec<&EffectCnt>) -> EffectCnt {
+  EffectCnt{
+    clean_rec:    median_by(cnts, &|c:&EffectCnt| c.clean_rec),
+    clean_eval:   median_by(cnts, &|c:&EffectCnt| c.clean_eval),
+    clean_edge:   median_by(cnts, &|c:&EffectCnt| c.clean_edge),
+    dirty:        median_by(cnts, &|c:&EffectCnt| c.dirty),
+    remove:       median_by(cnts, &|c:&EffectCnt| c.remove),
+    alloc_fresh:  median_by(cnts, &|c:&EffectCnt| c.alloc_fresh),
+    alloc_exists: median_by(cnts, &|c:&EffectCnt| c.alloc_exists),
+    force_miss:   median_by(cnts, &|c:&EffectCnt| c.force_miss),
+    force_hit:    median_by(cnts, &|c:&EffectCnt| c.force_hit),
+    force_refget: median_by(cnts, &|c:&EffectCnt| c.force_refget),
+  }
+}
+
+fn misses_by_ns_rec(traces:&Vec<trace::Trace>, table:&mut BTreeMap<String,usize>) {
+  for tr in traces.iter() {
+    match tr.effect {
+      trace::Effect::Force(trace::ForceCase::CompCacheMiss) => {
+        let path = &tr.edge.succ.loc.path;
+        *table.entry(string_of_path_prefix(path, path.len())).or_insert(0) += 1
+      },
+      _ => (),
+    };
+    misses_by_ns_rec(&tr.extent, table)
+  }
+}
+
+/// An explanation of the cost of one batch, e.g., one where the DCG
+/// engine is slower than the naive engine, in comparison with the
+/// median batch of the same lab.
+#[derive(Clone,Debug)]
+pub struct BatchExplanation {
+  pub batch_name:       usize,
+  pub editor:           EffectCnt,
+  pub median_editor:    EffectCnt,
+  pub archivist:        EffectCnt,
+  pub median_archivist: EffectCnt,
+  /// Cache misses of the archivist, by namespace (the path of each
+  /// location); most first.
+  pub misses_by_ns:     Vec<(String, usize)>,
+  /// The archivist's most often (re-)evaluated locations; most first.
+  pub top_evals:        Vec<HotSpot<Loc>>,
+}
+
+/// Explains the given batch of the given engine: its effect counts,
+/// versus the field-wise median over the lab's updates of the same
+/// kind (all batches but the first, when there are any, that edit the
+/// input, or else that change only the demand; See
+/// `Sample::demand_only`); its cache misses by namespace; and its
+/// most evaluated locations.  None if there is no such batch.
+/// Requires reflected traces.
+pub fn explain_batch(samples:&Vec<Sample>, engine:&EngineConfig, batch_name:usize) -> Option<BatchExplanation> {
+  let (this, demand_only) = match samples.iter().find(|s| s.batch_name == batch_name)
+    .and_then(|s| s.engine_sample(engine).map(|es| (es, s.demand_only))) {
+      None => return None,
+      Some(s) => s,
+    };
+  let updates : Vec<&EngineSample> = samples.iter()
+    .filter(|s| s.batch_name > 0 || samples.len() == 1)
+    .filter(|s| s.demand_only == demand_only)
+    .filter_map(|s| s.engine_sample(engine)).collect();
+  let editor_cnts    : Vec<&EffectCnt> = updates.iter().map(|s| &s.process_input.effect_cnt).collect();
+  let archivist_cnts : Vec<&EffectCnt> = updates.iter().map(|s| &s.compute_output.effect_cnt).collect();
+  let median_editor    = median_effect_cnt(&editor_cnts);
+  let median_archivist = median_effect_cnt(&archivist_cnts);
+
+  let mut misses = BTreeMap::new();
+  misses_by_ns_rec(&this.compute_output.reflect_traces, &mut misses);
+  let mut misses_by_ns : Vec<(String, usize)> = misses.into_iter().collect();
+  misses_by_ns.sort_by(|&(_, m1), &(_, m2)| m2.cmp(&m1));
+
+  let mut table = HashMap::new();
+  hot_spots_rec(batch_name, &this.compute_output.reflect_traces, &mut table);
+  let (top_evals, _) = rank_hot_spots_table(table);
+
+  Some(BatchExplanation{
+    batch_name,
+    editor:    this.process_input.effect_cnt.clone(),
+    median_editor,
+    archivist: this.compute_output.effect_cnt.clone(),
+    median_archivist,
+    misses_by_ns,
+    top_evals,
+  })
+}
@@ -8,7 +8,13 @@ use std::collections::HashMap;
 use adapton::engine::Name;
 use adapton::engine::reflect::*;
 use adapton::engine::reflect::{trace, string_of_name, string_of_loc};
-use labdef::{LabParams,Lab,LabResults, Sample};
+use labdef::{LabParams,Lab,LabResults, Sample, EngineConfig, Role};
+use labcheck::DcgDiagnostic;
+use labquery::{Impact, output_provenance, impacts};
+use labfit::{ComplexityFit, fit_cost_model};
+use labstats::{EffectCnt, NsCnt, HotSpot, DirtyFootprint, Garbage, BatchExplanation};
+use labstats::{ns_cnts_of_traces, depth_stats_of_traces, hot_spots, dirty_footprints, garbage_of_samples};
+use labstats::explain_batch;
 
 /// The `Div` struct represents a restricted form of a `<div>` element
 /// in HTML.  The field `tag` is a string, which corresponds to a
@@ -121,6 +127,20 @@ pub fn div_of_edge (e:&trace::Edge) -> Div {
                div_of_succ(&e.succ) ]) }
 }
 
+/// A CSS class that identifies a location, e.g., for selecting (and
+/// highlighting) its reflections in the generated HTML; unlike the
+/// string of a location, it contains only legal characters.  We
+/// escape every other character (including `_`) as `_hex_`, so that
+/// distinct locations give distinct classes.
+pub fn css_class_of_loc (l:&Loc) -> String {
+  let mut s = String::from("loc-");
+  for c in string_of_loc(l).chars() {
+    if c.is_alphanumeric() { s.push(c) }
+    else { s.push_str(&format!("_{:x}_", c as u32)) }
+  };
+  s
+}
+
 pub fn div_of_value_tree (dcg:&DCG, visited:&mut HashMap<Loc, ()>, val:&Val) -> Div {
   let div = Div {
     tag: match *val {
@@ -132,7 +152,7 @@ pub fn div_of_value_tree (dcg:&DCG, visited:&mut HashMap<Loc, ()>, val:&Val) ->
       })},
       Val::Tuple(ref vs) => { format!("val-tuple tuple-{}", vs.len()) },
       Val::Vec(ref vs) => { format!("val-vec vec-{}", vs.len()) },
-      Val::Art(ref loc, _) => { format!("val-art {}", string_of_loc( loc ) ) },
+      Val::Art(ref loc, _) => { format!("val-art {} {}", string_of_loc( loc ), css_class_of_loc( loc ) ) },
       Val::ValTODO => { format!("val-TODO") },
       Val::Name(ref n) => { format!("name val-name {}", string_of_name(n)) },
     },
@@ -207,7 +227,11 @@ pub fn div_of_force_tree (dcg:&DCG, visited:&mut HashMap<Loc, ()>, loc:&Loc) ->
   };
   visited.insert( loc.clone(), () );
   let no_extent = match dcg.table.get( loc ) {
-    None => panic!("dangling pointer in reflected DCG!"),
+    None => { 
+      // Dangling pointer in reflected DCG; See `labcheck::check_dcg`.
+      div.classes.push(String::from("dangling"));
+      true
+    },
     Some( nd ) => {
       match succs_of_node( nd ) {
         None => true, // No succs; E.g., ref cells have no succs
@@ -240,7 +264,11 @@ pub fn div_of_alloc_tree (dcg:&DCG, visited:&mut HashMap<Loc, ()>, loc:&Loc) ->
   };
   visited.insert( loc.clone(), () );
   let no_extent = match dcg.table.get( loc ) {
-    None => panic!("dangling pointer in reflected DCG!"),
+    None => { 
+      // Dangling pointer in reflected DCG; See `labcheck::check_dcg`.
+      div.classes.push(String::from("dangling"));
+      true
+    },
     Some( nd ) => {
       match succs_of_node( nd ) {
         None => true, // No succs; E.g., ref cells have no succs
@@ -264,6 +292,22 @@ pub fn div_of_alloc_tree (dcg:&DCG, visited:&mut HashMap<Loc, ()>, loc:&Loc) ->
   div
 }
 
+/// A short, human-readable name for each case of `trace::Effect`.
+pub fn string_of_trace_effect (eff:&trace::Effect) -> &'static str {
+  match *eff {
+    trace::Effect::CleanRec  => "CleanRec",
+    trace::Effect::CleanEval => "CleanEval",
+    trace::Effect::CleanEdge => "CleanEdge",
+    trace::Effect::Dirty     => "Dirty",
+    trace::Effect::Remove    => "Remove",
+    trace::Effect::Alloc(trace::AllocCase::LocFresh,_)     => "Alloc(LocFresh)",
+    trace::Effect::Alloc(trace::AllocCase::LocExists,_)    => "Alloc(LocExists)",
+    trace::Effect::Force(trace::ForceCase::CompCacheMiss)  => "Force(CompCacheMiss)",
+    trace::Effect::Force(trace::ForceCase::CompCacheHit)   => "Force(CompCacheHit)",
+    trace::Effect::Force(trace::ForceCase::RefGet)         => "Force(RefGet)",
+  }
+}
+
 pub fn div_of_trace (tr:&trace::Trace) -> Div {
   // For linking to rustdoc documentation from the output HTML
   let tr_eff_url = "http://adapton.org/rustdoc/adapton/engine/reflect/trace/enum.Effect.html";
@@ -291,18 +335,7 @@ pub fn div_of_trace (tr:&trace::Trace) -> Div {
           Div{ 
             tag: String::from("tr-effect"),
             text: Some(              
-              format!("<a href={:?}>{}</a>", tr_eff_url, match tr.effect {
-                trace::Effect::CleanRec  => "CleanRec",
-                trace::Effect::CleanEval => "CleanEval",
-                trace::Effect::CleanEdge => "CleanEdge",
-                trace::Effect::Dirty     => "Dirty",
-                trace::Effect::Remove    => "Remove",
-                trace::Effect::Alloc(trace::AllocCase::LocFresh,_)     => "Alloc(LocFresh)",
-                trace::Effect::Alloc(trace::AllocCase::LocExists,_)    => "Alloc(LocExists)",
-                trace::Effect::Force(trace::ForceCase::CompCacheMiss)  => "Force(CompCacheMiss)",
-                trace::Effect::Force(trace::ForceCase::CompCacheHit)   => "Force(CompCacheHit)",
-                trace::Effect::Force(trace::ForceCase::RefGet)         => "Force(RefGet)",
-              })),
+              format!("<a href={:?}>{}</a>", tr_eff_url, string_of_trace_effect(&tr.effect))),
             classes: vec![],
             extent: Box::new(vec![]),
           },
@@ -373,7 +406,7 @@ impl<T:WriteHTML> WriteHTML for Vec<T> {
   }
 }
 
-pub fn write_all_lab_results(_params:&LabParams, 
+pub fn write_all_lab_results(params:&LabParams, 
                               labs:&Vec<Box<Lab>>, 
                               results:&Vec<LabResults>) 
 {
@@ -387,19 +420,83 @@ pub fn write_all_lab_results(_params:&LabParams,
   assert!( labs.len() == results.len() );
 
   writeln!(writer, "<div class={:?}>Lab results summary</div>", "labsum-title").unwrap();
+  writeln!(writer, "<a class={:?} href=./catalog.html>catalog</a>", "lab-details").unwrap();
+  writeln!(writer, "<div class={:?}>rng: {}, seeds: {:?}</div>", "lab-meta",
+           params.rng.name(), params.sample_params.input_seeds).unwrap();
+
+  // Cost-model calibration, across all labs; we use the DCG without
+  // reflection, since reflection costs time that no counter captures.
+  let cost_model = fit_cost_model(results, &EngineConfig::DCGNoReflect);
+  match cost_model {
+    None => (),
+    Some((ref model, _)) => {
+      writeln!(writer, "<div class={:?}>Cost model (ns): {:.*} fixed + {:.*} per dirty + {:.*} per clean \
+                        + {:.*} per eval + {:.*} per create (r² = {:.*})</div>",
+               "cost-model", 
+               1, model.fixed, 1, model.dirty, 1, model.clean, 
+               1, model.eval, 1, model.create, 3, model.r2).unwrap();
+    }
+  };
 
-  for ((_i,lab),(_j,_result)) in 
+  for ((_i,lab),(j,result)) in 
     labs.iter().enumerate().zip(results.iter().enumerate()) 
   {
     writeln!(&mut writer, "<div class={:?}>", "labsum-row").unwrap();
     writeln!(&mut writer, "<div class={:?}>", "labsum-name").unwrap();
     write_lab_name(&mut writer, lab, false);
     writeln!(&mut writer, "</div>").unwrap();
+    write_lab_meta(&mut writer, lab);
+
+    match cost_model {
+      Some((_, ref residuals)) if residuals[j].samples > 0 => {
+        writeln!(&mut writer, "<div class={:?} title=\"mean residual (ns): {:.*}\">residual {:.*}%</div>", 
+                 "cost-residual", 1, residuals[j].mean, 1, 100.0 * residuals[j].mean_rel).unwrap();
+      },
+      _ => (),
+    };
+
+    write_dirty_footprint_chart(&mut writer, &dirty_footprints(&result.samples, &EngineConfig::DCG));
+
+    let diag_count = result.samples.iter().fold(0, |c, sample| {
+      sample.engine_samples.iter().fold(c, |c, s| {
+        c + s.process_input.dcg_diagnostics.len() + s.compute_output.dcg_diagnostics.len()
+      })
+    });
+    let invalid_inputs = result.samples.iter()
+      .filter(|s| s.input_valid == Some(false)).count();
+    let invalid_outputs = result.samples.iter()
+      .filter(|s| s.input_valid != Some(false) && s.output_valid == Some(false)).count();
+    if invalid_inputs > 0 {
+      writeln!(&mut writer, "<div class={:?}>{} invalid inputs</div>", "invalid-input", invalid_inputs).unwrap();
+    };
+    if invalid_outputs > 0 {
+      writeln!(&mut writer, "<div class={:?}>{} invalid outputs</div>", "invalid", invalid_outputs).unwrap();
+    };
+    let invalid_observations = result.samples.iter().filter(|s| s.input_valid != Some(false)).fold(0, |c, sample| {
+      sample.engine_samples.iter().fold(c, |c, s| {
+        c + s.observations.iter().filter(|o| o.valid == Some(false)).count()
+      })
+    });
+    if invalid_observations > 0 {
+      writeln!(&mut writer, "<div class={:?}>{} invalid observations</div>", "invalid", invalid_observations).unwrap();
+    };
+    if diag_count > 0 {
+      writeln!(&mut writer, "<div class={:?}>{} DCG diagnostics</div>", "invalid", diag_count).unwrap();
+    };
+    write_lab_errors(&mut writer, result);
     
     writeln!(&mut writer, "<a class={:?} href=./{}/traces.html>details</a>", 
              "lab-details", 
              string_of_name(&lab.name())
     ).unwrap();
+    writeln!(&mut writer, "<a class={:?} href=./{}/hotspots.html>hot spots</a>", 
+             "lab-details", 
+             string_of_name(&lab.name())
+    ).unwrap();
+    writeln!(&mut writer, "<a class={:?} href=./{}/garbage.html>garbage</a>", 
+             "lab-details", 
+             string_of_name(&lab.name())
+    ).unwrap();
 
     writeln!(&mut writer, "</div>").unwrap();        
     write_cr(&mut writer);
@@ -428,6 +525,57 @@ pub fn write_lab_name<W:Write>(writer:&mut W, lab:&Box<Lab>, is_title:bool) {
   ).unwrap();
 }
 
+/// Writes the tags and expected update complexity of the lab (See
+/// `LabMeta`), with its description as a tooltip.
+pub fn write_lab_meta<W:Write>(writer:&mut W, lab:&Box<Lab>) {
+  let meta = lab.meta();
+  writeln!(writer, "<div class={:?} title={:?}>", "lab-meta", meta.descr).unwrap();
+  for tag in meta.tags.iter() {
+    writeln!(writer, "<div class={:?}>{}</div>", "lab-tag", tag.name()).unwrap();
+  };
+  match meta.update_complexity {
+    None => (),
+    Some(ref c) => writeln!(writer, "<div class={:?}>expected update: {}</div>", "lab-expected", c.name()).unwrap(),
+  };
+  writeln!(writer, "</div>").unwrap();
+}
+
+/// Writes a documentation page for the catalog of labs, from their
+/// metadata (See `LabMeta`): for each lab, its description, tags,
+/// input and output types, whether it honors demand, its expected
+/// update complexity, and the knobs that it sets (See `LabKnobs`).
+pub fn write_catalog_doc(labs:&Vec<Box<Lab>>) {
+  fs::create_dir_all("lab-results").unwrap();
+  let f = File::create(format!("lab-results/catalog.html")).unwrap();
+  let mut writer = BufWriter::new(f);
+
+  writeln!(writer, "{}", style_string()).unwrap();
+  writeln!(writer, "<div class={:?}>Lab catalog</div>", "labsum-title").unwrap();
+
+  for lab in labs.iter() {
+    let meta = lab.meta();
+    writeln!(writer, "<div class=\"hot-spots\">").unwrap();
+    write_lab_name(&mut writer, lab, false);
+    write_lab_meta(&mut writer, lab);
+    writeln!(writer, "<div class={:?}>{}</div>", "lab-descr", meta.descr).unwrap();
+    writeln!(writer, "<div class=\"ns-row\"><div class=\"ns-path\">input</div><div class=\"hot-batches\">{}</div></div>",
+             meta.input_type).unwrap();
+    writeln!(writer, "<div class=\"ns-row\"><div class=\"ns-path\">output</div><div class=\"hot-batches\">{}</div></div>",
+             meta.output_type).unwrap();
+    writeln!(writer, "<div class=\"ns-row\"><div class=\"ns-path\">honors demand</div><div class=\"hot-batches\">{}</div></div>",
+             if meta.honors_demand { "yes" } else { "no" }).unwrap();
+    writeln!(writer, "<div class=\"ns-row\"><div class=\"ns-path\">expected update</div><div class=\"hot-batches\">{}</div></div>",
+             match meta.update_complexity { None => "unknown", Some(ref c) => c.name() }).unwrap();
+    if lab.knobs().knobs.len() > 0 {
+      writeln!(writer, "<div class=\"ns-row\"><div class=\"ns-path\">knobs</div><div class=\"hot-batches\">{}</div></div>",
+               lab.knobs().knobs.iter().map(|k| format!("{} = {}", k.0, k.1)).collect::<Vec<_>>().join(", ")).unwrap();
+    };
+    writeln!(writer, "</div>").unwrap();
+    write_cr(&mut writer);
+  }
+  writer.flush().unwrap();
+}
+
 pub fn write_dcg_edge_tree<W:Write> (writer:&mut W, dcg:&DCG, traces:&Vec<trace::Trace>, effect:Effect) {
   for tr in traces.iter() {
     if tr.edge.succ.effect == effect {
@@ -449,12 +597,22 @@ pub fn write_sample_dcg<W:Write>
    prev_sample:Option<&Sample>,
    this_sample:&Sample)
 {
+  let batch_name = this_sample.batch_name;
+  // The DCG trees come from the (reused) DCG engine's sample, if any.
+  let this_sample = match this_sample.engine_sample(&EngineConfig::DCG) {
+    None => return,
+    Some(s) => s,
+  };
+  let prev_sample = match prev_sample {
+    None => None,
+    Some(s) => s.engine_sample(&EngineConfig::DCG),
+  };
   write_cr(writer)
     ;
-  match this_sample.dcg_sample.process_input.reflect_dcg {
+  match this_sample.process_input.reflect_dcg {
     None => { },
     Some(ref dcg_post_edit) => {
-      match this_sample.dcg_sample.input {
+      match this_sample.input {
         None => { },
         Some(ref input) => {
           writeln!(writer, "<div class=\"input-value\">").unwrap();
@@ -467,13 +625,26 @@ pub fn write_sample_dcg<W:Write>
     }
   }
   ;
-  match this_sample.dcg_sample.compute_output.reflect_dcg {
+  match this_sample.compute_output.reflect_dcg {
     None => { },
     Some(ref dcg_post_update) => {      
-      match this_sample.dcg_sample.output {
+      match this_sample.output {
         None => { },
         Some(ref output) => {
-          writeln!(writer, "<div class=\"output-value\">").unwrap();
+          // Provenance of each output location, for highlighting its
+          // input locations when the user hovers over it.
+          match this_sample.input {
+            None => (),
+            Some(ref input) => {
+              writeln!(writer, "<script>provenance[{}] = {{", batch_name).unwrap();
+              for (loc, prov) in output_provenance(dcg_post_update, input, output).into_iter() {
+                writeln!(writer, "{:?}: {:?},", css_class_of_loc(&loc), 
+                         prov.iter().map(css_class_of_loc).collect::<Vec<_>>()).unwrap();
+              }
+              writeln!(writer, "}};</script>").unwrap();
+            }
+          };
+          writeln!(writer, "<div class=\"output-value\" data-batch=\"{}\">", batch_name).unwrap();
           writeln!(writer, "<div class=\"label\">{}</div>", "Output:").unwrap();
           div_of_value_tree(dcg_post_update, &mut HashMap::new(), output)
             .write_html( writer );            
@@ -486,7 +657,7 @@ pub fn write_sample_dcg<W:Write>
   // Separate the input and output from the DCG trees, below
   write_cr(writer);
   ;
-  match this_sample.dcg_sample.process_input.reflect_dcg {
+  match this_sample.process_input.reflect_dcg {
     Some(ref dcg_post_edit) => {
       match prev_sample {
         Some(ref prev_sample) => {
@@ -496,7 +667,7 @@ pub fn write_sample_dcg<W:Write>
           write_dcg_edge_tree
             (writer, 
              dcg_post_edit,
-             &prev_sample.dcg_sample.compute_output.reflect_traces,
+             &prev_sample.compute_output.reflect_traces,
              Effect::Alloc
             );
           writeln!(writer, "</div>").unwrap();
@@ -507,7 +678,7 @@ pub fn write_sample_dcg<W:Write>
           write_dcg_edge_tree
             (writer, 
              dcg_post_edit,
-             &prev_sample.dcg_sample.compute_output.reflect_traces,
+             &prev_sample.compute_output.reflect_traces,
              Effect::Force,         
             );
           writeln!(writer, "</div>").unwrap();
@@ -525,7 +696,7 @@ pub fn write_sample_dcg<W:Write>
   ;
   writeln!(writer,"<div class=\"archivist-update-sep\"></div>").unwrap();
  
-  match this_sample.dcg_sample.compute_output.reflect_dcg {
+  match this_sample.compute_output.reflect_dcg {
     Some(ref dcg_post_update) => {
       
       // 3/4: alloc tree for compute, after the update
@@ -534,7 +705,7 @@ pub fn write_sample_dcg<W:Write>
       write_dcg_edge_tree
         (writer, 
          dcg_post_update,
-         &this_sample.dcg_sample.compute_output.reflect_traces,
+         &this_sample.compute_output.reflect_traces,
          Effect::Alloc
         );
       writeln!(writer, "</div>").unwrap();
@@ -545,7 +716,7 @@ pub fn write_sample_dcg<W:Write>
       write_dcg_edge_tree
         (writer, 
          dcg_post_update,
-         &this_sample.dcg_sample.compute_output.reflect_traces,
+         &this_sample.compute_output.reflect_traces,
          Effect::Force,         
         );
       writeln!(writer, "</div>").unwrap();
@@ -560,7 +731,146 @@ pub fn write_sample_dcg<W:Write>
 
 }
 
-pub fn write_lab_results_traces(_params:&LabParams, lab:&Box<Lab>, results:&LabResults) {
+pub fn write_dcg_diagnostics<W:Write>(writer:&mut W, engine:&EngineConfig, role:&Role, diags:&Vec<DcgDiagnostic>) {
+  if diags.len() == 0 { return };
+  writeln!(writer, "<div class=\"dcg-diagnostics\">").unwrap();
+  writeln!(writer, "<div class=\"label\">{} DCG diagnostics, post-{}:</div>", engine.name(), role.name()).unwrap();
+  for diag in diags.iter() {
+    writeln!(writer, "<div class=\"dcg-diagnostic\">{}</div>", diag.descr()).unwrap();
+  }
+  writeln!(writer, "</div>").unwrap();
+}
+
+/// Writes a bar chart of effect counts over the batches of a lab, for
+/// the given engine and role: Each bar stacks the dirtying, cleaning,
+/// re-evaluation, removals, cache hits, reference gets and
+/// allocations of one batch.
+pub fn write_effect_chart<W:Write>(writer:&mut W, engine:&EngineConfig, role:&Role, samples:&Vec<Sample>) {
+  let max_height = 100;
+  let cnts : Vec<(usize,&EffectCnt)> = samples.iter().filter_map(|sample| {
+    sample.engine_sample(engine).map(|s| (sample.batch_name, &s.metrics(role).effect_cnt))
+  }).collect();
+  let max_total = cnts.iter().fold(0, |m, &(_, c)| {
+    let total = c.dirty + c.clean_rec + c.clean_edge + c.clean_eval + c.force_miss + c.remove
+      + c.hits() + c.force_refget + c.allocs();
+    if total > m { total } else { m }
+  });
+  if max_total == 0 { return };
+  writeln!(writer, "<div class=\"effect-chart\">").unwrap();
+  writeln!(writer, "<div class=\"label\">{} effects, {}:</div>", engine.name(), role.name()).unwrap();
+  for &(batch_name, c) in cnts.iter() {
+    writeln!(writer, "<div class=\"effect-bar\" title=\"batch {}: {:?}\">", batch_name, c).unwrap();
+    for &(class, n) in [ ("bar-alloc",  c.allocs()),
+                         ("bar-refget", c.force_refget),
+                         ("bar-hit",    c.hits()),
+                         ("bar-remove", c.remove),
+                         ("bar-eval",   c.clean_eval + c.force_miss),
+                         ("bar-clean", c.clean_rec + c.clean_edge),
+                         ("bar-dirty", c.dirty) ].iter() {
+      writeln!(writer, "<div class=\"{}\" style=\"height:{}px\"></div>", 
+               class, n * max_height / max_total).unwrap();
+    }
+    writeln!(writer, "</div>").unwrap();
+  }
+  writeln!(writer, "<div class=\"effect-legend\">\
+                    <div class=\"bar-dirty\">dirty</div> <div class=\"bar-clean\">clean</div> \
+                    <div class=\"bar-eval\">eval</div> <div class=\"bar-remove\">remove</div> \
+                    <div class=\"bar-hit\">hit</div> <div class=\"bar-refget\">ref get</div> \
+                    <div class=\"bar-alloc\">alloc</div></div>").unwrap();
+  writeln!(writer, "</div>").unwrap();
+}
+
+/// Writes a chart of the dirtying footprint of each batch (See
+/// `labstats::dirty_footprints`): Each bar stacks the fraction of
+/// dirtied edges that the archivist cleaned without re-evaluation,
+/// and the fraction that forced re-evaluation.  Batches that dirty
+/// nothing (e.g., the first batch) have no bar.
+pub fn write_dirty_footprint_chart<W:Write>(writer:&mut W, footprints:&Vec<DirtyFootprint>) {
+  let max_height = 40.0;
+  let footprints : Vec<&DirtyFootprint> = footprints.iter().filter(|f| f.dirtied > 0).collect();
+  if footprints.len() == 0 { return };
+  let (dirtied, cleaned, re_evaluated) = footprints.iter().fold((0,0,0), |(d,c,e), f| {
+    (d + f.dirtied, c + f.cleaned, e + f.re_evaluated)
+  });
+  writeln!(writer, "<div class=\"dirty-footprint\">").unwrap();
+  for f in footprints.iter() {
+    let clean_ratio = f.clean_ratio().min(1.0);
+    let eval_ratio  = f.eval_ratio().min(1.0 - clean_ratio);
+    writeln!(writer, "<div class=\"effect-bar\" style=\"height:{}px\" \
+                      title=\"batch {}: dirtied {}, cleaned {}, re-evaluated {}\">\
+                      <div class=\"bar-eval\" style=\"height:{}px\"></div>\
+                      <div class=\"bar-clean\" style=\"height:{}px\"></div></div>",
+             max_height, f.batch_name, f.dirtied, f.cleaned, f.re_evaluated,
+             (eval_ratio * max_height) as usize,
+             (clean_ratio * max_height) as usize).unwrap();
+  }
+  writeln!(writer, "<div class=\"dirty-footprint-lab\">dirtied {}, cleaned {:.*}%, re-evaluated {:.*}%</div>",
+           dirtied,
+           1, 100.0 * (cleaned as f64) / (dirtied as f64),
+           1, 100.0 * (re_evaluated as f64) / (dirtied as f64)).unwrap();
+  writeln!(writer, "</div>").unwrap();
+}
+
+/// Writes an explanation of a batch (See `labstats::explain_batch`),
+/// in comparison with the median batch of the lab.
+pub fn write_batch_explanation<W:Write>(writer:&mut W, expl:&BatchExplanation) {
+  let max_items = 5;
+  writeln!(writer, "<div class=\"explanation\">").unwrap();
+  writeln!(writer, "<div class=\"label\">Why? (vs. median batch)</div>").unwrap();
+  writeln!(writer, "<div class=\"row\">Editor dirtied {} edges (median {}).</div>",
+           expl.editor.dirty, expl.median_editor.dirty).unwrap();
+  writeln!(writer, "<div class=\"row\">Archivist: {} cache misses (median {}), \
+                    {} re-evaluations while cleaning (median {}), \
+                    {} cache hits (median {}).</div>",
+           expl.archivist.force_miss, expl.median_archivist.force_miss,
+           expl.archivist.clean_eval, expl.median_archivist.clean_eval,
+           expl.archivist.force_hit,  expl.median_archivist.force_hit).unwrap();
+  writeln!(writer, "<div class=\"row\">Archivist allocated {} fresh locations (median {}), \
+                    and reused {} locations (median {}).</div>",
+           expl.archivist.alloc_fresh,  expl.median_archivist.alloc_fresh,
+           expl.archivist.alloc_exists, expl.median_archivist.alloc_exists).unwrap();
+  if expl.misses_by_ns.len() > 0 {
+    writeln!(writer, "<div class=\"row\">Cache misses by namespace: {}.</div>",
+             expl.misses_by_ns.iter().take(max_items).map(|&(ref ns, m)| {
+               format!("{} ({})", if ns.len() == 0 { "(root)" } else { ns.as_str() }, m)
+             }).collect::<Vec<_>>().join(", ")).unwrap();
+  };
+  if expl.top_evals.len() > 0 {
+    writeln!(writer, "<div class=\"row\">Most evaluated: {}.</div>",
+             expl.top_evals.iter().take(max_items).map(|spot| {
+               format!("{} ({})", string_of_loc(&spot.key), spot.evals)
+             }).collect::<Vec<_>>().join(", ")).unwrap();
+  };
+  writeln!(writer, "</div>").unwrap();
+}
+
+/// Writes a table of effect counts, aggregated by namespace (See
+/// `labstats::ns_cnts_of_traces`).
+pub fn write_ns_cnts<W:Write>(writer:&mut W, role:&Role, ns_cnts:&Vec<NsCnt>) {
+  if ns_cnts.len() == 0 { return };
+  writeln!(writer, "<div class=\"ns-cnts\">").unwrap();
+  writeln!(writer, "<div class=\"label\">Namespaces, {}:</div>", role.name()).unwrap();
+  writeln!(writer, "<div class=\"ns-row ns-header\"><div class=\"ns-path\">namespace</div>\
+                    <div class=\"ns-cnt\">hits</div><div class=\"ns-cnt\">misses</div>\
+                    <div class=\"ns-cnt\">allocs</div><div class=\"ns-cnt\">dirty</div>\
+                    <div class=\"ns-cnt\">clean</div><div class=\"ns-cnt\">depth</div>\
+                    <div class=\"ns-cnt\">chain</div></div>").unwrap();
+  for ns_cnt in ns_cnts.iter() {
+    writeln!(writer, "<div class=\"ns-row\"><div class=\"ns-path\" style=\"padding-left:{}px\">{}</div>\
+                      <div class=\"ns-cnt\">{}</div><div class=\"ns-cnt\">{}</div>\
+                      <div class=\"ns-cnt\">{}</div><div class=\"ns-cnt\">{}</div>\
+                      <div class=\"ns-cnt\">{}</div><div class=\"ns-cnt\">{}</div>\
+                      <div class=\"ns-cnt\">{}</div></div>",
+             ns_cnt.depth * 8,
+             if ns_cnt.depth == 0 { "(root)" } else { ns_cnt.namespace.as_str() },
+             ns_cnt.cnt.hits(), ns_cnt.cnt.misses(), ns_cnt.cnt.allocs(),
+             ns_cnt.cnt.dirty, ns_cnt.cnt.cleans(),
+             ns_cnt.max_trace_depth, ns_cnt.max_force_chain).unwrap();
+  }
+  writeln!(writer, "</div>").unwrap();
+}
+
+pub fn write_lab_results_traces(params:&LabParams, lab:&Box<Lab>, results:&LabResults) {
   
   let labname = string_of_name( &lab.name() );
   //let laburl  = lab.url();
@@ -576,52 +886,126 @@ pub fn write_lab_results_traces(_params:&LabParams, lab:&Box<Lab>, results:&LabR
   writeln!(writer, "{}", style_string()).unwrap();
   
   write_lab_name(&mut writer, lab, true);
+  writeln!(writer, "<div class=\"lab-meta\">rng: {}, seeds: {:?}</div>",
+           results.rng.name(), params.sample_params.input_seeds).unwrap();
 
   writeln!(writer, "<div style=\"font-size:12px\" class=\"batch-name\"> step</div>").unwrap();
   writeln!(writer, "<div style=\"font-size:20px\" class=\"editor\">Editor</div>").unwrap();
   writeln!(writer, "<div style=\"font-size:20px\" class=\"archivist\">Archivist</div>").unwrap();
+  write_cr(&mut writer);
+
+  // Effect histograms, over all batches
+  for role in vec![Role::Editor, Role::Archivist] {
+    write_effect_chart(&mut writer, &EngineConfig::DCG, &role, &results.samples);
+  }
   
+  let batch_impacts : Vec<Impact> = impacts(&results.samples, &EngineConfig::DCG);
+
   let mut prev_sample = None;
   for sample in results.samples.iter() {
     write_cr(&mut writer);
     // - - - - - - - 
     // 0. Write batch name (a counter); and write timing information for this edit batch.
+    writeln!(writer, "<a name=\"batch-{}\"></a>", sample.batch_name).unwrap();
     writeln!(writer, "<div class=\"batch-name-lab\">batch name<div class=\"batch-name\">{:?}</div></div>", 
              sample.batch_name).unwrap();
+    if sample.demand_only {
+      writeln!(writer, "<div class=\"demand-only\">Demand only: {}</div>", sample.demand).unwrap();
+    };
 
     writeln!(writer, "<div class=\"editor\">").unwrap();
-    writeln!(writer, "<div class=\"time-ns-lab\">time (ns): <div class=\"time-ns\">{:?}</div></div>", 
-             sample.dcg_sample.process_input.time_ns).unwrap();    
+    for engine_sample in sample.engine_samples.iter() {
+      writeln!(writer, "<div class=\"row\">").unwrap();
+      writeln!(writer, "<div class=\"time-ns-lab\">{} time (ns): <div class=\"time-ns\">{:?}</div></div>", 
+               engine_sample.engine.name(), engine_sample.process_input.time_ns).unwrap();    
+      match engine_sample.replay_time_ns {
+        Some(ns) => writeln!(writer, "<div class=\"time-ns-lab\">{} replay (ns): <div class=\"time-ns\">{:?}</div></div>", 
+                             engine_sample.engine.name(), ns).unwrap(),
+        None => (),
+      };
+      writeln!(writer, "</div>").unwrap();
+    }
     writeln!(writer, "</div>").unwrap();
 
     writeln!(writer, "<div class=\"archivist\">").unwrap();
 
-    writeln!(writer, "<div class=\"row\">").unwrap();
-    writeln!(writer, "<div class=\"time-ns-lab\">Naive time (ns): <div class=\"time-ns\">{:?}</div></div>", 
-             sample.naive_sample.compute_output.time_ns).unwrap();    
+    for engine_sample in sample.engine_samples.iter() {
+      writeln!(writer, "<div class=\"row\">").unwrap();
+      writeln!(writer, "<div class=\"time-ns-lab\">{} time (ns): <div class=\"time-ns\">{:?}</div></div>", 
+               engine_sample.engine.name(), engine_sample.compute_output.time_ns).unwrap();    
+
+      writeln!(writer, "<div class=\"time-ms-lab\">{} time (ms): <div class=\"time-ms\">{:.*}</div></div>", 
+               engine_sample.engine.name(), 
+               2, (engine_sample.compute_output.time_ns as f64) / (1000000 as f64)).unwrap();    
+      if engine_sample.compute_output.reflect_traces.len() > 0 {
+        let stats = depth_stats_of_traces(&engine_sample.compute_output.reflect_traces);
+        writeln!(writer, "<div class=\"depth-lab\" title=\"trace widths: {:?}\">trace depth: {}, widest level: {}, force chain: {}</div>",
+                 stats.widths, stats.max_depth, stats.max_width(), stats.force_chain).unwrap();
+      };
+      for obs in engine_sample.observations.iter() {
+        writeln!(writer, "<div class=\"time-ns-lab\">{} observe {} (ns): <div class=\"time-ns\">{:?}</div></div>", 
+                 engine_sample.engine.name(), obs.name, obs.metrics.time_ns).unwrap();
+      };
+      writeln!(writer, "</div>").unwrap();
+    }
 
-    writeln!(writer, "<div class=\"time-ms-lab\">Naive time (ms): <div class=\"time-ms\">{:.*}</div></div>", 
-             2, (sample.naive_sample.compute_output.time_ns as f64) / (1000000 as f64)).unwrap();    
-    writeln!(writer, "</div>").unwrap();
-             
-    writeln!(writer, "<div class=\"row\">").unwrap();
-    writeln!(writer, "<div class=\"time-ns-lab\">DCG time (ns): <div class=\"time-ns\">{:?}</div></div>", 
-             sample.dcg_sample.compute_output.time_ns).unwrap();    
+    // Impact of this batch's edit, versus the work of the update
+    match batch_impacts.iter().find(|i| i.batch_name == sample.batch_name) {
+      None => (),
+      Some(impact) => {
+        writeln!(writer, "<div class=\"row impact\" title=\"changed: {}\">Impact: {} changed input locations; \
+                          slice of {} nodes ({} computations, {} outputs); \
+                          re-evaluated {}, of which {} fresh, and {} outside the slice</div>",
+                 impact.changed.iter().map(string_of_loc).collect::<Vec<_>>().join(", "),
+                 impact.changed.len(), impact.slice_nodes, impact.slice_comps, impact.affected_outputs,
+                 impact.re_evaluated, impact.fresh, impact.unnecessary).unwrap();
+      }
+    };
 
-    writeln!(writer, "<div class=\"time-ms-lab\">DCG time (ms): <div class=\"time-ms\">{:.*}</div></div>", 
-             2, (sample.dcg_sample.compute_output.time_ns as f64) / (1000000 as f64)).unwrap();    
-    writeln!(writer, "</div>").unwrap();
-    
-    if sample.naive_sample.compute_output.time_ns <
-      sample.dcg_sample.compute_output.time_ns {
-      writeln!(writer, "<div class=\"overhead-lab\">DCG Overhead: <div class=\"overhead\">{:.*}</div></div>", 
-             2, ( (sample.dcg_sample.compute_output.time_ns  as f64) / 
-                   (sample.naive_sample.compute_output.time_ns as f64) )).unwrap();      
-    } else {      
-      writeln!(writer, "<div class=\"speedup-lab\">DCG Speedup: <div class=\"speedup\">{:.*}</div></div>", 
-             2, ( (sample.naive_sample.compute_output.time_ns  as f64) / 
-                   (sample.dcg_sample.compute_output.time_ns as f64) )).unwrap();
-    }    
+    // Compare each engine to the reference engine
+    match sample.engine_sample(&params.reference_engine) {
+      None => (),
+      Some(reference) => {
+        for engine_sample in sample.engine_samples.iter() {
+          if engine_sample.engine == reference.engine { continue };
+          writeln!(writer, "<div class=\"row\">").unwrap();
+          if reference.compute_output.time_ns <
+            engine_sample.compute_output.time_ns {
+              writeln!(writer, "<div class=\"overhead-lab\">{} Overhead: <div class=\"overhead\">{:.*}</div></div>", 
+                       engine_sample.engine.name(),
+                       2, ( (engine_sample.compute_output.time_ns  as f64) / 
+                             (reference.compute_output.time_ns as f64) )).unwrap();      
+            } else {      
+              writeln!(writer, "<div class=\"speedup-lab\">{} Speedup: <div class=\"speedup\">{:.*}</div></div>", 
+                       engine_sample.engine.name(),
+                       2, ( (reference.compute_output.time_ns  as f64) / 
+                             (engine_sample.compute_output.time_ns as f64) )).unwrap();
+            }
+          if reference.compute_output.time_ns < engine_sample.compute_output.time_ns &&
+            engine_sample.compute_output.reflect_traces.len() > 0 {
+              match explain_batch(&results.samples, &engine_sample.engine, sample.batch_name) {
+                None => (),
+                Some(expl) => write_batch_explanation(&mut writer, &expl),
+              }
+            };
+          if engine_sample.input_valid == Some(false) {
+            // The outputs are incomparable; the editor is at fault, not the archivist.
+            writeln!(writer, "<div class=\"invalid-input\">Input differs from {}</div>", 
+                     reference.engine.name()).unwrap();
+          } else if engine_sample.output_valid == Some(false) {
+            writeln!(writer, "<div class=\"invalid\">Output differs from {}</div>", 
+                     reference.engine.name()).unwrap();
+          }
+          if engine_sample.input_valid != Some(false) {
+            for obs in engine_sample.observations.iter().filter(|o| o.valid == Some(false)) {
+              writeln!(writer, "<div class=\"invalid\">Observation {} differs from {}</div>", 
+                       obs.name, reference.engine.name()).unwrap();
+            }
+          };
+          writeln!(writer, "</div>").unwrap();
+        }
+      }
+    };
     writeln!(writer, "</div>").unwrap();
     write_cr(&mut writer);    
     
@@ -630,20 +1014,34 @@ pub fn write_lab_results_traces(_params:&LabParams, lab:&Box<Lab>, results:&LabR
     // 3. Write last DCG, after edit but before update.
     // 4. Write DCG of the update.
     write_sample_dcg(&mut writer, lab, prev_sample, sample);      
+
+    for engine_sample in sample.engine_samples.iter() {
+      for role in vec![Role::Editor, Role::Archivist] {
+        write_dcg_diagnostics(&mut writer, &engine_sample.engine, &role, &engine_sample.metrics(&role).dcg_diagnostics);
+      }
+    }
     
-    if sample.dcg_sample.compute_output.reflect_traces.len() == 0 {
+    let dcg_sample = sample.engine_sample(&EngineConfig::DCG);
+    if dcg_sample.map(|s| s.compute_output.reflect_traces.len()).unwrap_or(0) == 0 {
       // 5 & 6. No traces to write.
     } else {
+      let dcg_sample = dcg_sample.unwrap();
+      // - - - - - - - 
+      // 5 & 6, in aggregate: Effects by namespace, for editor and archivist
+      for role in vec![Role::Editor, Role::Archivist] {
+        write_ns_cnts(&mut writer, &role, &ns_cnts_of_traces(&dcg_sample.metrics(&role).reflect_traces));
+      }
+      write_cr(&mut writer);
       // - - - - - - - 
       // 5. Write traces of editor
       
       writeln!(writer, "<div class=\"traces-box\">").unwrap();
       // writeln!(writer, "<div class=\"time-ns-lab\">time (ns): <div class=\"time-ns\">{:?}</div></div>", 
-      //          sample.dcg_sample.process_input.time_ns).unwrap();    
+      //          dcg_sample.process_input.time_ns).unwrap();    
       // writeln!(writer, "<div class=\"traces-lab\">Traces (<a href={:?}>doc</a>)</div>", trace_url).unwrap();    
       writeln!(writer, "<div class=\"label\">{}</div>", "Editor trace:").unwrap();
       writeln!(writer, "<div class=\"traces\">").unwrap();
-      for tr in sample.dcg_sample.process_input.reflect_traces.iter() {
+      for tr in dcg_sample.process_input.reflect_traces.iter() {
         div_of_trace(tr).write_html(&mut writer)
       }
       writeln!(writer, "</div>").unwrap();   
@@ -656,7 +1054,7 @@ pub fn write_lab_results_traces(_params:&LabParams, lab:&Box<Lab>, results:&LabR
       writeln!(writer, "<div class=\"traces-box\">").unwrap();
       writeln!(writer, "<div class=\"label\">{}</div>", "Archivist trace:").unwrap();
       writeln!(writer, "<div class=\"traces\">").unwrap();
-      for tr in sample.dcg_sample.compute_output.reflect_traces.iter() {
+      for tr in dcg_sample.compute_output.reflect_traces.iter() {
         div_of_trace(tr).write_html(&mut writer)
       }
       writeln!(writer, "</div>").unwrap();    
@@ -667,9 +1065,153 @@ pub fn write_lab_results_traces(_params:&LabParams, lab:&Box<Lab>, results:&LabR
     // - - - - - - - - - - - - - - -       
     prev_sample = Some(sample) ; // Must be last!
   }
+  // The editor's failure, or the progress (if any), ends the samples.
+  write_lab_errors(&mut writer, results);
   writer.flush().unwrap();  
 }
 
+/// Writes the failures of the lab's editor, if any (See `LabError`),
+/// and why the run stopped early, if it did (See `LabResults::stopped`).
+pub fn write_lab_errors<W:Write>(writer:&mut W, results:&LabResults) {
+  for err in results.errors.iter() {
+    writeln!(writer, "<div class=\"lab-error\">Editor failed in batch {}, {}: {}</div>",
+             err.batch_name, err.engine.name(), err.error.descr()).unwrap();
+  }
+  match results.stopped {
+    Some(ref reason) => writeln!(writer, "<div class=\"lab-error\">Stopped after batch {}: {}</div>",
+                                 results.samples.len().saturating_sub(1), reason).unwrap(),
+    None => (),
+  }
+}
+
+fn write_hot_spot_rows<W:Write,K>(writer:&mut W, spots:&Vec<HotSpot<K>>, 
+                                  key_string:&Fn(&K) -> String, max_rows:usize) {
+  writeln!(writer, "<div class=\"ns-row ns-header\"><div class=\"ns-path\">computation</div>\
+                    <div class=\"ns-cnt\">re-evals</div><div class=\"ns-cnt\">evals</div>\
+                    <div class=\"hot-batches\">batches</div></div>").unwrap();
+  for spot in spots.iter().take(max_rows) {
+    writeln!(writer, "<div class=\"ns-row\"><div class=\"ns-path\">{}</div>\
+                      <div class=\"ns-cnt\">{}</div><div class=\"ns-cnt\">{}</div>\
+                      <div class=\"hot-batches\">",
+             key_string(&spot.key), spot.re_evals, spot.evals).unwrap();
+    for b in spot.batches.iter() {
+      writeln!(writer, "<a href=\"./traces.html#batch-{}\">{}</a>", b, b).unwrap();
+    }
+    writeln!(writer, "</div></div>").unwrap();
+  }
+}
+
+/// Writes the hot spots of a lab (See `labstats::hot_spots`): the
+/// memoized computations that the DCG engine re-evaluates most often
+/// over all batches, by location and by namespace.  Each batch links
+/// into the lab's trace view.
+pub fn write_lab_hot_spots(_params:&LabParams, lab:&Box<Lab>, results:&LabResults) {
+  let labname = string_of_name( &lab.name() );
+  fs::create_dir_all(format!("lab-results/{}/", labname)).unwrap();
+  let f = File::create(format!("lab-results/{}/hotspots.html", labname)).unwrap();
+  let mut writer = BufWriter::new(f);
+  
+  writeln!(writer, "{}", style_string()).unwrap();
+  write_lab_name(&mut writer, lab, true);
+
+  let (by_loc, by_ns) = hot_spots(&results.samples, &EngineConfig::DCG);
+  
+  writeln!(writer, "<div class=\"hot-spots\">").unwrap();
+  writeln!(writer, "<div class=\"label\">Hot spots, by namespace:</div>").unwrap();
+  write_hot_spot_rows(&mut writer, &by_ns, &|ns:&String| 
+                      if ns.len() == 0 { String::from("(root)") } else { ns.clone() }, 100);
+  writeln!(writer, "</div>").unwrap();
+  write_cr(&mut writer);
+
+  writeln!(writer, "<div class=\"hot-spots\">").unwrap();
+  writeln!(writer, "<div class=\"label\">Hot spots, by location:</div>").unwrap();
+  write_hot_spot_rows(&mut writer, &by_loc, &|l:&Loc| string_of_loc(l), 100);
+  writeln!(writer, "</div>").unwrap();
+  writer.flush().unwrap();
+}
+
+/// Writes the garbage of a lab's DCG after each batch (See
+/// `labstats::garbage_of_samples`): a chart of its growth, and for
+/// each batch, the namespaces of the unreachable nodes.
+pub fn write_lab_garbage(_params:&LabParams, lab:&Box<Lab>, results:&LabResults) {
+  let labname = string_of_name( &lab.name() );
+  fs::create_dir_all(format!("lab-results/{}/", labname)).unwrap();
+  let f = File::create(format!("lab-results/{}/garbage.html", labname)).unwrap();
+  let mut writer = BufWriter::new(f);
+  
+  writeln!(writer, "{}", style_string()).unwrap();
+  write_lab_name(&mut writer, lab, true);
+
+  let garbage : Vec<Garbage> = garbage_of_samples(&results.samples, &EngineConfig::DCG);
+  let max_nodes = garbage.iter().fold(0, |m, g| if g.nodes > m { g.nodes } else { m });
+  let max_height = 100;
+
+  writeln!(writer, "<div class=\"effect-chart\">").unwrap();
+  writeln!(writer, "<div class=\"label\">DCG nodes, unreachable (of total), per batch:</div>").unwrap();
+  for g in garbage.iter() {
+    writeln!(writer, "<div class=\"effect-bar\" title=\"batch {}: {} unreachable, {} new, of {} nodes\">\
+                      <div class=\"bar-dirty\" style=\"height:{}px\"></div>\
+                      <div class=\"bar-alloc\" style=\"height:{}px\"></div></div>",
+             g.batch_name, g.garbage.len(), g.new_garbage, g.nodes,
+             if max_nodes == 0 { 0 } else { g.garbage.len() * max_height / max_nodes },
+             if max_nodes == 0 { 0 } else { (g.nodes - g.garbage.len()) * max_height / max_nodes }).unwrap();
+  }
+  writeln!(writer, "</div>").unwrap();
+  write_cr(&mut writer);
+
+  for g in garbage.iter() {
+    writeln!(writer, "<div class=\"hot-spots\">").unwrap();
+    writeln!(writer, "<div class=\"label\">Batch <a href=\"./traces.html#batch-{}\">{}</a>: \
+                      {} unreachable ({} new), of {} nodes</div>",
+             g.batch_name, g.batch_name, g.garbage.len(), g.new_garbage, g.nodes).unwrap();
+    for &(ref ns, count) in g.by_ns.iter() {
+      writeln!(writer, "<div class=\"ns-row\"><div class=\"ns-path\">{}</div><div class=\"ns-cnt\">{}</div></div>",
+               if ns.len() == 0 { "(root)" } else { ns.as_str() }, count).unwrap();
+    }
+    writeln!(writer, "</div>").unwrap();
+    write_cr(&mut writer);
+  }
+  writer.flush().unwrap();
+}
+
+/// Writes the fitted asymptotics of each lab, over a sweep of input
+/// sizes (See `labfit::fit_size_sweep`): for each measure, the
+/// log-log slope, and each candidate complexity with its goodness of
+/// fit, best first.
+pub fn write_complexity_fits(_params:&LabParams, labs:&Vec<Box<Lab>>, fits:&Vec<Vec<ComplexityFit>>) {
+  fs::create_dir_all("lab-results").unwrap();
+  let f = File::create(format!("lab-results/complexity.html")).unwrap();
+  let mut writer = BufWriter::new(f);
+
+  writeln!(writer, "{}", style_string()).unwrap();
+  writeln!(writer, "<div class={:?}>Fitted complexity, over input sizes</div>", "labsum-title").unwrap();
+
+  assert!( labs.len() == fits.len() );
+
+  for (lab, lab_fits) in labs.iter().zip(fits.iter()) {
+    writeln!(writer, "<div class=\"hot-spots\">").unwrap();
+    write_lab_name(&mut writer, lab, false);
+    write_lab_meta(&mut writer, lab);
+    writeln!(writer, "<div class=\"ns-row ns-header\"><div class=\"ns-path\">measure</div>\
+                      <div class=\"ns-cnt\">log-log slope</div><div class=\"ns-cnt\">r²</div>\
+                      <div class=\"hot-batches\">models (r²)</div></div>").unwrap();
+    for fit in lab_fits.iter() {
+      writeln!(writer, "<div class=\"ns-row\" title=\"{:?}\"><div class=\"ns-path\">{}: <b>{}</b></div>\
+                        <div class=\"ns-cnt\">{:.*}</div><div class=\"ns-cnt\">{:.*}</div>\
+                        <div class=\"hot-batches\">",
+               fit.points, fit.measure.name(), fit.best().complexity.name(),
+               2, fit.log_slope, 3, fit.log_r2).unwrap();
+      for model in fit.models.iter() {
+        writeln!(writer, "{} ({:.*}) ", model.complexity.name(), 3, model.r2).unwrap();
+      }
+      writeln!(writer, "</div></div>").unwrap();
+    }
+    writeln!(writer, "</div>").unwrap();
+    write_cr(&mut writer);
+  }
+  writer.flush().unwrap();
+}
+
 pub fn style_string() -> &'static str {
 "
 <html>
@@ -773,6 +1315,193 @@ hr {
   background: #ffcccc;
   border: solid 1px red;
 }
+.invalid {
+  font-size: 14px;
+  display: inline;
+  color: white;
+  background: #cc0000;
+  border: solid 1px red;
+}
+.lab-error {
+  font-size: 14px;
+  display: inline;
+  color: white;
+  background: #660066;
+  border: solid 1px red;
+}
+.demand-only {
+  font-size: 14px;
+  display: inline;
+  color: black;
+  background: #aaddff;
+  padding: 2px;
+}
+.invalid-input {
+  font-size: 14px;
+  display: inline;
+  color: black;
+  background: #ffcc00;
+  border: solid 1px red;
+}
+.effect-chart {
+  display: block;
+  float: left;
+  font-size: 12px;
+  color: #dd88ff;
+  background: #331144;
+  border-radius: 5px;
+  margin: 2px;
+  padding: 2px;
+  width: 49%;
+}
+.effect-bar {
+  display: inline-flex;
+  flex-direction: column;
+  justify-content: flex-end;
+  height: 100px;
+  width: 8px;
+  margin: 1px;
+}
+.effect-bar div {
+  display: block;
+  width: 8px;
+}
+.effect-legend {
+  display: block;
+}
+.bar-dirty { background: #ffaaaa; }
+.bar-clean { background: #aaaaff; }
+.bar-eval  { background: #8888ff; color: white; }
+.bar-hit   { background: #ccccff; }
+.bar-alloc { background: #ccffcc; }
+.bar-remove { background: #aaaaaa; }
+.bar-refget { background: #eeeeaa; }
+.dirty-footprint {
+  display: inline-block;
+  font-size: 12px;
+  color: #dd88ff;
+  margin: 2px;
+  background: #331144;
+}
+.dirty-footprint .effect-bar {
+  background: #ffaaaa;
+}
+.dirty-footprint-lab {
+  display: inline-block;
+  padding: 2px;
+}
+.impact {
+  font-size: 12px;
+  margin: 4px;
+  color: #444444;
+}
+.depth-lab {
+  font-size: 12px;
+  margin: 4px;
+  color: #444444;
+}
+.explanation {
+  display: block;
+  font-size: 12px;
+  margin: 4px;
+  padding: 4px;
+  color: #880000;
+  background: #ffeeee;
+  border: solid 1px red;
+}
+.ns-cnts {
+  display: block;
+  float: left;
+  font-size: 12px;
+  color: #dd88ff;
+  background: #331144;
+  border-radius: 5px;
+  margin: 2px;
+  width: 49%;
+}
+.ns-row {
+  display: block;
+}
+.ns-header {
+  font-weight: bold;
+}
+.ns-path {
+  display: inline-block;
+  width: 37%;
+}
+.ns-cnt {
+  display: inline-block;
+  width: 9%;
+  text-align: right;
+}
+.hot-spots {
+  display: block;
+  float: left;
+  font-size: 12px;
+  color: #dd88ff;
+  background: #331144;
+  border-radius: 5px;
+  margin: 8px;
+  padding: 4px;
+  width: 90%;
+}
+.hot-spots a {
+  color: #ccaadd;
+  margin: 1px;
+}
+.hot-batches {
+  display: inline-block;
+  padding-left: 8px;
+}
+.cost-model {
+  display: block;
+  margin: 8px;
+  font-size: 14px;
+  color: #ccaadd;
+}
+.lab-meta {
+  display: inline;
+  margin: 3px;
+}
+.lab-tag {
+  display: inline;
+  font-size: 12px;
+  color: #ccaadd;
+  border: solid #ccaadd 1px;
+  border-radius: 3px;
+  padding: 1px 3px;
+  margin: 1px;
+}
+.lab-expected {
+  display: inline;
+  font-size: 12px;
+  color: #ccaadd;
+  margin: 3px;
+}
+.lab-descr {
+  font-size: 14px;
+  margin: 4px;
+}
+.cost-residual {
+  font-size: 14px;
+  color: #ccaadd;
+  margin: 3px;
+}
+.dcg-diagnostics {
+  display: block;
+  float: left;
+  clear: both;
+  font-size: 12px;
+  color: #ffaaaa;
+  background: #331144;
+  margin: 2px;
+}
+.dcg-diagnostic {
+  display: block;
+}
+.dangling {
+  background: red;
+}
 .speedup {
   font-size: 30px;
   display: inline;
@@ -978,6 +1707,11 @@ hr {
 .output-value {
   width: 49%;
 }
+.provenance,
+.provenance-out {
+  background-color: #ffff88;
+  border-color: #ffff00;
+}
 .traces-box {
   width: 99%;
 }
@@ -1039,6 +1773,34 @@ function toggleNames() {
  }
 }
 
+// Provenance of output locations, by batch; See `labquery::output_provenance`.
+var provenance = {};
+
+function locClassOf(el) {
+ var cs = el.className.split(/\\s+/);
+ for (var i = 0; i < cs.length; i++) {
+   if (cs[i].indexOf('loc-') == 0) { return cs[i] }
+ }
+ return null;
+}
+
+$(function() {
+ $('.output-value .val-art').hover(function(e) {
+   var out  = $(this).closest('.output-value');
+   var prov = provenance[out.data('batch')] || {};
+   var ins  = prov[locClassOf(this)] || [];
+   var inp  = out.prevAll('.input-value').first();
+   $(this).addClass('provenance-out');
+   for (var i = 0; i < ins.length; i++) {
+     inp.find('.' + ins[i]).first().addClass('provenance');
+   }
+   e.stopPropagation();
+ }, function() {
+   $('.provenance').removeClass('provenance');
+   $('.provenance-out').removeClass('provenance-out');
+ });
+});
+
 function toggleEffects() {
  var selection = document.getElementById(\"checkbox-3\");
  if (selection.checked) {
@@ -0,0 +1,187 @@
+//! For more information and instructions, see the [Adapton Lab
+//! README](https://github.com/cuplv/adapton-lab.rust).
+//!
+//! To run your own labs through the harness, register them in a
+//! `labreg::LabRegistry` and call `run_all_tests`.
+
+#![feature(field_init_shorthand)]
+//#![feature(rustc_private)]
+//#![feature(custom_derive)]
+
+//extern crate serialize;
+//extern crate csv;
+extern crate rand;
+
+#[macro_use]
+extern crate adapton;
+
+extern crate pmfp_collections;
+
+/// Defines lab parameters `LabParams` and `LabDef`, the parameters
+/// for running the test diagram from the [Adapton Lab
+/// README](https://github.com/cuplv/adapton-lab.rust).
+pub mod labdef;
+
+/// This module of Adapton Lab extends Adapton's existing reflection
+/// (see `adapton::engine::reflect`) with further reflections.  In
+/// particular, we produce HTML output structure, for human user
+/// interaction and consumption.
+pub mod labviz;
+
+/// **Generically implements** the test diagram in the [Adapton Lab
+/// README](https://github.com/cuplv/adapton-lab.rust).
+pub mod labrun;
+
+/// Checks lab results for problems other than invalid output, e.g.,
+/// experiments that are not deterministic.
+pub mod labcheck;
+
+/// Statistics over the reflected traces and DCGs of lab results,
+/// e.g., effect counts, aggregated by namespace.
+pub mod labstats;
+
+/// Fits models to lab results, e.g., asymptotic complexity over a
+/// sweep of input sizes.
+pub mod labfit;
+
+/// Queries over reflected DCGs, e.g., the provenance of output in input.
+pub mod labquery;
+
+/// Observers of lab outputs, e.g., forcing a prefix of a lazy list.
+pub mod labobs;
+
+/// A registry of labs to run; See `LabRegistry`.
+pub mod labreg;
+
+/// Random number generators for labs, whose raw streams are fixed by
+/// their seeds; See `LabRngGen`.
+pub mod labrng;
+
+/// Progress of lab runs, e.g., streaming samples, or stopping early;
+/// See `LabProgress`.
+pub mod labprog;
+
+/// Provides **concrete instances** of the test diagram from the
+/// [Adapton Lab README](https://github.com/cuplv/adapton-lab.rust).
+pub mod catalog;
+
+use labdef::*;
+use labreg::LabRegistry;
+use labprog::{LabProgress, NoProgress};
+use adapton::engine::reflect::string_of_name;
+
+// fn csv_of_runtimes(path:&str, samples: Vec<Sample>) {
+//   let path = Path::new(path);
+//   let mut writer = csv::Writer::from_file(path).unwrap();
+//   for r in samples.into_iter() {
+//     //println!("{:?}",r);
+//     //writer.encode(r).ok().expect("CSV writer error");
+//   }
+// }
+
+/// The default lab parameters, e.g., for running the catalog.
+pub fn lab_params_defaults() -> LabParams {
+  
+  let do_reflect = true;
+  //let do_reflect = false;
+  
+  return LabParams {
+    sample_params: SampleParams {
+      input_seeds: vec![0],
+      generate_params: GenerateParams {
+        size: 8,
+        //size: 16,
+        //size: 32,
+        //size: 64,
+        //size: 256,
+        //size: 1024,
+        //size: 10000,
+        //size: 20000,
+        //size: 50000,
+        //size: 100000,
+        //size: 200000,
+        gauge:1,
+        nominal_strategy: NominalStrategy::Regular,
+      },
+      demand: 6,
+      validate_output: true,
+      validate_input: true,
+      change_batch_size: 1,
+      reflect_dcg: do_reflect,
+      reflect_trace: do_reflect,
+    },
+    change_batch_loopc:10,
+    engines: vec![ EngineConfig::Naive,
+                   EngineConfig::DCG,
+                   EngineConfig::DCGFromScratch,
+                   EngineConfig::DCGNoReflect ],
+    reference_engine: EngineConfig::Naive,
+    demand_change: None,
+    rng: LabRng::Pcg32,
+  }
+}
+
+/// Runs each lab of the registry, and writes its results (as HTML)
+/// into the current directory, along with a summary page for all of
+/// them.  This is the library entry point for crates that define
+/// their own labs (See `LabRegistry`).  Runs each lab to its last
+/// batch, quietly (See `NoProgress`; the command line adds
+/// `PrintProgress`).
+pub fn run_all_tests(params:&LabParams, registry:&LabRegistry) {
+  run_all_tests_with(params, registry, &|_lab| Box::new(NoProgress) as Box<LabProgress>)
+}
+
+/// Like `run_all_tests`, but with the progress that `progress_of_lab`
+/// gives for each lab, e.g., to stream its samples to disk, or to
+/// stop it early (See `labprog`).
+pub fn run_all_tests_with(params:&LabParams, registry:&LabRegistry,
+                          progress_of_lab:&Fn(&Box<Lab>) -> Box<LabProgress>) {
+  let labs   = registry.labs();
+  let mut results = vec![];
+  println!("Params: {:?}", params );
+  for lab in labs.iter() {
+    println!("Running lab: {}", string_of_name( &lab.name() ) );
+    let mut progress = progress_of_lab(lab);
+    let result = lab.run_with(params, &mut *progress);
+    match result.stopped {
+      Some(ref reason) => println!("Stopped lab: {}: {}", string_of_name( &lab.name() ), reason ),
+      None => (),
+    };
+    labviz::write_lab_results_traces(params, lab, &result);
+    labviz::write_lab_hot_spots(params, lab, &result);
+    labviz::write_lab_garbage(params, lab, &result);
+    results.push(result);
+  }  
+  labviz::write_all_lab_results(params, labs, &results);
+  labviz::write_catalog_doc(labs);
+}
+
+/// Runs each lab twice, with identical parameters, and reports the
+/// first divergence between the two runs (if any).
+pub fn check_all_determinism(params:&LabParams, registry:&LabRegistry) -> bool {
+  let labs   = registry.labs();
+  let mut all_deterministic = true;
+  for lab in labs.iter() {
+    match labcheck::check_determinism(lab, params) {
+      None => println!("Deterministic lab: {}", string_of_name( &lab.name() ) ),
+      Some(d) => {
+        println!("Nondeterministic lab: {}: {:?}", string_of_name( &lab.name() ), d );
+        all_deterministic = false;
+      }
+    }
+  };
+  all_deterministic
+}
+
+/// Runs each lab over a sweep of input sizes, and fits the measured
+/// times and counts to candidate asymptotic complexities.
+pub fn run_all_size_sweeps(params:&LabParams, registry:&LabRegistry, sizes:&Vec<usize>) {
+  let labs   = registry.labs();
+  let mut fits = vec![];
+  for lab in labs.iter() {
+    println!("Sweeping lab: {}", string_of_name( &lab.name() ) );
+    let sweep = labfit::run_size_sweep(lab, params, sizes);
+    fits.push(labfit::fit_size_sweep(&sweep));
+  }
+  labviz::write_complexity_fits(params, labs, &fits);
+}
@@ -1,103 +1,95 @@
 //! For more information and instructions, see the [Adapton Lab
 //! README](https://github.com/cuplv/adapton-lab.rust).
 
-#![feature(field_init_shorthand)]
-//#![feature(rustc_private)]
-//#![feature(custom_derive)]
-
-//extern crate serialize;
-//extern crate csv;
-extern crate rand;
-
-#[macro_use]
+extern crate adapton_lab;
 extern crate adapton;
 
-extern crate pmfp_collections;
-
-/// Defines lab parameters `LabParams` and `LabDef`, the parameters
-/// for running the test diagram from the [Adapton Lab
-/// README](https://github.com/cuplv/adapton-lab.rust).
-pub mod labdef;
-
-/// This module of Adapton Lab extends Adapton's existing reflection
-/// (see `adapton::engine::reflect`) with further reflections.  In
-/// particular, we produce HTML output structure, for human user
-/// interaction and consumption.
-pub mod labviz;
-
-/// **Generically implements** the test diagram in the [Adapton Lab
-/// README](https://github.com/cuplv/adapton-lab.rust).
-pub mod labrun;
-
-/// Provides **concrete instances** of the test diagram from the
-/// [Adapton Lab README](https://github.com/cuplv/adapton-lab.rust).
-pub mod catalog;
-
-use labdef::*;
+use adapton_lab::*;
+use adapton_lab::labdef::{Lab, LabTag, LabRng, DemandChange};
+use adapton_lab::labreg::LabRegistry;
+use adapton_lab::labprog::{LabProgress, Progresses, PrintProgress, StopOnInvalid, TimeBudget, StreamSamples};
 use adapton::engine::reflect::string_of_name;
 
-// fn csv_of_runtimes(path:&str, samples: Vec<Sample>) {
-//   let path = Path::new(path);
-//   let mut writer = csv::Writer::from_file(path).unwrap();
-//   for r in samples.into_iter() {
-//     //println!("{:?}",r);
-//     //writer.encode(r).ok().expect("CSV writer error");
-//   }
-// }
-
-fn lab_params_defaults() -> LabParams {
-  
-  let do_reflect = true;
-  //let do_reflect = false;
-  
-  return LabParams {
-    sample_params: SampleParams {
-      input_seeds: vec![0],
-      generate_params: GenerateParams {
-        size: 8,
-        //size: 16,
-        //size: 32,
-        //size: 64,
-        //size: 256,
-        //size: 1024,
-        //size: 10000,
-        //size: 20000,
-        //size: 50000,
-        //size: 100000,
-        //size: 200000,
-        gauge:1,
-        nominal_strategy: NominalStrategy::Regular,
-      },
-      demand: 6,
-      validate_output: true,
-      change_batch_size: 1,
-      reflect_dcg: do_reflect,
-      reflect_trace: do_reflect,
-    },
-    change_batch_loopc:10,
+#[test]
+fn test_all() { run_all_tests(&lab_params_defaults(), &LabRegistry::catalog()) }
+#[test]
+fn test_determinism() { assert!( check_all_determinism(&lab_params_defaults(), &LabRegistry::catalog()) ) }
+fn main2() { 
+  let args : Vec<String> = std::env::args().collect();
+  let mut params = lab_params_defaults();
+  if args.iter().any(|a| a == "--demand-changes") {
+    // Every other batch changes only the demand, up to the input size.
+    params.demand_change = Some(DemandChange{
+      period: 2,
+      max_demand: params.sample_params.generate_params.size,
+    });
+  };
+  // Optionally, choose the random number generator, e.g., `--rng xorshift64star`.
+  match args.iter().position(|a| a == "--rng") {
+    None => (),
+    Some(i) => {
+      let name = args.get(i + 1).map(|n| n.as_str()).unwrap_or("");
+      match LabRng::all().into_iter().find(|r| r.name() == name) {
+        Some(rng) => { params.rng = rng },
+        None => {
+          println!("Unknown rng {:?}; expected one of: {}", name,
+                   LabRng::all().iter().map(|r| r.name()).collect::<Vec<_>>().join(", "));
+          std::process::exit(1)
+        }
+      }
+    }
+  };
+  let mut registry = LabRegistry::catalog();
+  // Optionally, run only the labs with a given tag, e.g., `--tag lazy`.
+  match args.iter().position(|a| a == "--tag") {
+    None => (),
+    Some(i) => {
+      let name = args.get(i + 1).map(|n| n.as_str()).unwrap_or("");
+      match registry.tag_of_name(name) {
+        Some(tag) => { registry.retain_tagged(&tag); },
+        None => {
+          let mut tags = LabTag::all();
+          for t in registry.tags().into_iter() { if !tags.contains(&t) { tags.push(t) } };
+          println!("Unknown tag {:?}; expected one of: {}", name,
+                   tags.iter().map(|t| t.name()).collect::<Vec<_>>().join(", "));
+          std::process::exit(1)
+        }
+      }
+    }
+  };
+  if args.iter().any(|a| a == "--check-determinism") {
+    if !check_all_determinism(&params, &registry) { std::process::exit(1) }
+  } else if args.iter().any(|a| a == "--sweep-sizes") {
+    run_all_size_sweeps(&params, &registry, &vec![ 8, 16, 32, 64, 128, 256 ])
+  } else {
+    // Optionally, stop each lab early, or stream its samples, e.g., `--time-budget-ms 1000`.
+    let stop_on_invalid = args.iter().any(|a| a == "--stop-on-invalid");
+    let stream_samples  = args.iter().any(|a| a == "--stream-samples");
+    let time_budget_ms  = args.iter().position(|a| a == "--time-budget-ms").map(|i| {
+      args.get(i + 1).and_then(|n| n.parse::<u64>().ok()).unwrap_or_else(|| {
+        println!("Expected a number of milliseconds after --time-budget-ms");
+        std::process::exit(1)
+      })
+    });
+    run_all_tests_with(&params, &registry, &|lab| {
+      let mut progresses : Vec<Box<LabProgress>> = vec![ Box::new(PrintProgress) ];
+      if stop_on_invalid { progresses.push(Box::new(StopOnInvalid)) };
+      match time_budget_ms {
+        Some(ms) => progresses.push(Box::new(TimeBudget{ budget_ns: ms * 1_000_000 })),
+        None => (),
+      };
+      if stream_samples {
+        // Each lab streams to lab-results/<lab>/samples.tsv.
+        let dir = format!("lab-results/{}", string_of_name( &lab.name() ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let f = std::fs::File::create(format!("{}/samples.tsv", dir)).unwrap();
+        progresses.push(Box::new(StreamSamples::new(std::io::BufWriter::new(f))))
+      };
+      Box::new(Progresses{ progresses: progresses }) as Box<LabProgress>
+    })
   }
 }
 
-// TODO -- Put these implementations into a 'catalog' module.
-
-fn run_all_tests() {
-  let params = lab_params_defaults();
-  let labs   = catalog::all_labs();
-  let mut results = vec![];
-  println!("Params: {:?}", params );
-  for lab in labs.iter() {
-    println!("Running lab: {}", string_of_name( &lab.name() ) );
-    let result = lab.run(&params);
-    labviz::write_lab_results_traces(&params, lab, &result);
-    results.push(result);
-  }  
-  labviz::write_all_lab_results(&params, &labs, &results);
-}
-
-#[test]
-fn test_all() { run_all_tests() }
-fn main2() { run_all_tests() }
-
 fn main () {
   use std::thread;
   let child =